@@ -0,0 +1,104 @@
+//! Crawls a synthetic site served from a local keep-alive HTTP/1.1 server, to measure how well
+//! the worker threads reuse connections. Opening a connection waits `HANDSHAKE`, standing in for
+//! the TCP and TLS handshakes of a remote server, and every response waits `LATENCY`.
+//! Run with `cargo bench --bench crawl`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use link_checker::LinkChecker;
+use url::Url;
+
+const PAGES: usize = 2_000;
+const LINKS_PER_PAGE: usize = 20;
+const HANDSHAKE: Duration = Duration::from_millis(20);
+const LATENCY: Duration = Duration::from_millis(1);
+const THREAD_COUNTS: [u32; 3] = [8, 16, 32];
+
+fn page(index: usize) -> String {
+    let mut seed = index as u64;
+    let links: String = (0..LINKS_PER_PAGE)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            format!(r#"<a href="/page-{}/">link</a>"#, (seed >> 33) as usize % PAGES)
+        })
+        .collect();
+    format!("<html><body>{}</body></html>", links)
+}
+
+fn handle_connection(stream: TcpStream, connections: &AtomicUsize) {
+    connections.fetch_add(1, Ordering::Relaxed);
+    thread::sleep(HANDSHAKE);
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+        let page_index = path
+            .strip_prefix("/page-")
+            .and_then(|rest| rest.trim_end_matches('/').parse::<usize>().ok())
+            .or((path == "/").then_some(0));
+        let (status, body) = match page_index {
+            Some(index) => (200, page(index)),
+            None => (404, String::new()),
+        };
+        thread::sleep(LATENCY);
+        let body = if method == "HEAD" { "" } else { body.as_str() };
+        let response = format!(
+            "HTTP/1.1 {} X\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Serve the site, returning its URL and a counter of the connections opened
+fn serve() -> (Url, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let counter = counter.clone();
+            thread::spawn(move || handle_connection(stream, &counter));
+        }
+    });
+    (Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap(), connections)
+}
+
+fn main() {
+    println!("{} pages with {} links each", PAGES, LINKS_PER_PAGE);
+    for threads in THREAD_COUNTS {
+        let (start_url, connections) = serve();
+        let start = Instant::now();
+        let results = LinkChecker::new().threads(threads).ignore_robots(true).domain_match(true).run(start_url);
+        let elapsed = start.elapsed();
+        println!(
+            "{:>2} threads: {:>8.2?} ({:>6.0} pages/s, {} connections, {} pages crawled)",
+            threads,
+            elapsed,
+            results.url_map.len() as f64 / elapsed.as_secs_f64(),
+            connections.load(Ordering::Relaxed),
+            results.url_map.len()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Compares two ways of deduplicating discovered links on a synthetic site:
+//!
+//! - `control thread`: workers send every link they find over a channel, and a single
+//!   thread owns the `CrawlState` and marks links as visited (the current design)
+//! - `shared mutex`: workers mark links in an `Arc<Mutex<CrawlState>>` themselves and
+//!   only send the links that weren't visited yet
+//!
+//! No requests are made, so this measures the deduplication overhead alone, which is the
+//! worst case for the control thread. Run with `cargo bench --bench visited_set`.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use link_checker::CrawlState;
+use url::Url;
+
+const PAGES: usize = 20_000;
+const LINKS_PER_PAGE: usize = 100;
+const THREAD_COUNTS: [usize; 3] = [1, 8, 32];
+
+type Site = Arc<Vec<Vec<Url>>>;
+/// Deduplicates a site's links with some number of threads, returning the time taken and the number of new URLs
+type Design = fn(&Site, &Url, usize) -> (Duration, usize);
+
+/// Every page's links: a shared navigation block plus links to pseudo-random pages
+fn synthetic_site(start_url: &Url) -> Vec<Vec<Url>> {
+    let mut seed: u64 = 0x5eed;
+    let mut next_page = || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % PAGES
+    };
+    let page_url = |page: usize| start_url.join(&format!("section-{}/page-{}/", page % 50, page)).unwrap();
+
+    let navigation: Vec<Url> = (0..LINKS_PER_PAGE / 2).map(page_url).collect();
+    (0..PAGES)
+        .map(|_| {
+            let mut links = navigation.clone();
+            links.extend((0..LINKS_PER_PAGE / 2).map(|_| page_url(next_page())));
+            links
+        })
+        .collect()
+}
+
+fn control_thread(site: &Site, start_url: &Url, threads: usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let (sender, receiver) = mpsc::channel::<Vec<Url>>();
+    for worker in 0..threads {
+        let site = site.clone();
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for links in site.iter().skip(worker).step_by(threads) {
+                sender.send(links.clone()).unwrap();
+            }
+        });
+    }
+    drop(sender);
+
+    let mut crawl_state = CrawlState::new(start_url, false);
+    let mut new_urls = 0;
+    for links in receiver {
+        for url in links {
+            if crawl_state.should_visit_url(&url) && crawl_state.mark_visited(&url) {
+                new_urls += 1;
+            }
+        }
+    }
+    (start.elapsed(), new_urls)
+}
+
+fn shared_mutex(site: &Site, start_url: &Url, threads: usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let crawl_state = Arc::new(Mutex::new(CrawlState::new(start_url, false)));
+    let (sender, receiver) = mpsc::channel::<Vec<Url>>();
+    for worker in 0..threads {
+        let site = site.clone();
+        let sender = sender.clone();
+        let crawl_state = crawl_state.clone();
+        thread::spawn(move || {
+            for links in site.iter().skip(worker).step_by(threads) {
+                let new_links: Vec<Url> = {
+                    let mut crawl_state = crawl_state.lock().unwrap();
+                    links
+                        .iter()
+                        .filter(|url| crawl_state.should_visit_url(url) && crawl_state.mark_visited(url))
+                        .cloned()
+                        .collect()
+                };
+                sender.send(new_links).unwrap();
+            }
+        });
+    }
+    drop(sender);
+
+    let new_urls = receiver.iter().map(|links| links.len()).sum();
+    (start.elapsed(), new_urls)
+}
+
+fn main() {
+    let start_url = Url::parse("https://example.com/").unwrap();
+    let site = Arc::new(synthetic_site(&start_url));
+    let total_links = PAGES * LINKS_PER_PAGE;
+    println!("{} pages with {} links each ({} links in total)", PAGES, LINKS_PER_PAGE, total_links);
+
+    for threads in THREAD_COUNTS {
+        let designs: [(&str, Design); 2] = [("control thread", control_thread), ("shared mutex", shared_mutex)];
+        for (name, run) in designs {
+            // Best of three runs, to smooth out scheduling noise
+            let (elapsed, new_urls) = (0..3).map(|_| run(&site, &start_url, threads)).min_by_key(|(elapsed, _)| *elapsed).unwrap();
+            let links_per_second = total_links as f64 / elapsed.as_secs_f64();
+            println!(
+                "{:>2} threads, {:<14}: {:>8.1?} ({:>5.2}M links/s, {} new URLs)",
+                threads,
+                name,
+                elapsed,
+                links_per_second / 1e6,
+                new_urls
+            );
+        }
+    }
+}
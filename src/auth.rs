@@ -0,0 +1,133 @@
+use url::Url;
+
+/// Authentication headers that are only ever sent to the start URL's host,
+/// so credentials never leak to external sites.
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    host: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl Credentials {
+    /// Create empty credentials scoped to the host of the start URL
+    pub fn new(start_url: &Url) -> Credentials {
+        Credentials {
+            host: start_url.host_str().map(str::to_string),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Add HTTP basic authentication from a `user:pass` string
+    pub fn with_basic_auth(mut self, user_pass: &str) -> Result<Credentials, String> {
+        if !user_pass.contains(':') {
+            return Err(format!("expected user:pass, got {:?}", user_pass));
+        }
+        let value = format!("Basic {}", base64_encode(user_pass.as_bytes()));
+        self.headers.push(("Authorization".to_string(), value));
+        Ok(self)
+    }
+
+    /// Add a raw header from a `Name: value` string, e.g. `Authorization: Bearer <token>`
+    pub fn with_header(mut self, header: &str) -> Result<Credentials, String> {
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(format!("expected \"Name: value\", got {:?}", header));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("missing header name in {:?}", header));
+        }
+        self.headers.push((name.to_string(), value.trim().to_string()));
+        Ok(self)
+    }
+
+    /// The headers to attach to a request for the given URL.
+    /// Empty unless the URL is on the same host as the start URL.
+    pub fn headers_for(&self, url: &Url) -> &[(String, String)] {
+        if self.host.is_some() && url.host_str() == self.host.as_deref() {
+            &self.headers
+        } else {
+            &[]
+        }
+    }
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (triple >> (18 - 6 * i)) & 0x3f;
+                encoded.push(ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"user:pass"), "dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn test_basic_auth_header() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let credentials = Credentials::new(&start_url).with_basic_auth("user:pass").unwrap();
+
+        assert_eq!(
+            credentials.headers_for(&start_url),
+            &[("Authorization".to_string(), "Basic dXNlcjpwYXNz".to_string())]
+        );
+        assert!(Credentials::new(&start_url).with_basic_auth("userpass").is_err());
+    }
+
+    #[test]
+    fn test_raw_header() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let credentials = Credentials::new(&start_url)
+            .with_header("Authorization: Bearer abc.def")
+            .unwrap();
+
+        assert_eq!(
+            credentials.headers_for(&start_url),
+            &[("Authorization".to_string(), "Bearer abc.def".to_string())]
+        );
+        assert!(Credentials::new(&start_url).with_header("no separator").is_err());
+        assert!(Credentials::new(&start_url).with_header(": value").is_err());
+    }
+
+    #[test]
+    fn test_headers_not_sent_to_other_domains() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let credentials = Credentials::new(&start_url)
+            .with_header("Authorization: Bearer secret")
+            .unwrap();
+
+        let same_host = Url::parse("https://example.com/other/page.html").unwrap();
+        assert_eq!(credentials.headers_for(&same_host).len(), 1);
+
+        let external_urls = vec![
+            "https://other.com/docs/",
+            "https://sub.example.com/docs/",
+            "https://example.com.evil.com/docs/",
+        ];
+        for url_str in external_urls {
+            let url = Url::parse(url_str).unwrap();
+            assert!(credentials.headers_for(&url).is_empty(), "Credentials leaked to {}", url_str);
+        }
+    }
+}
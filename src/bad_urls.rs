@@ -0,0 +1,152 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::BadUrl;
+
+/// The version of the bad_urls.json format, raised when a field is removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The contents of bad_urls.json: the broken links, with which crawl found them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BadUrlsReport {
+    pub schema_version: u32,
+    /// The URL the crawl started from, or none when checking a list of URLs or local files
+    pub start_url: Option<String>,
+    /// When the report was written, as a UTC timestamp like `2024-05-01T12:00:00Z`
+    pub generated_at: String,
+    /// The version of link-checker that wrote the report
+    pub tool_version: String,
+    pub bad_urls: Vec<BadUrl>,
+}
+
+/// Either format of bad_urls.json
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyBadUrls {
+    Report(BadUrlsReport),
+    /// Before the format was versioned, the file was a bare array of broken links
+    Legacy(Vec<BadUrl>),
+}
+
+impl BadUrlsReport {
+    /// A report of these broken links, written now by this version
+    pub fn new(start_url: Option<&Url>, bad_urls: Vec<BadUrl>) -> BadUrlsReport {
+        BadUrlsReport {
+            schema_version: SCHEMA_VERSION,
+            start_url: start_url.map(Url::to_string),
+            generated_at: format_timestamp(SystemTime::now()),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            bad_urls,
+        }
+    }
+}
+
+/// Read the broken links from a bad_urls.json, in either the versioned format or the bare
+/// array written by earlier versions
+pub fn read_bad_urls(path: &Path) -> std::io::Result<Vec<BadUrl>> {
+    let content = std::fs::read_to_string(path)?;
+    match serde_json::from_str(&content)? {
+        AnyBadUrls::Report(report) if report.schema_version > SCHEMA_VERSION => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("schema version {} is newer than this version of link-checker supports", report.schema_version),
+        )),
+        AnyBadUrls::Report(report) => Ok(report.bad_urls),
+        AnyBadUrls::Legacy(bad_urls) => Ok(bad_urls),
+    }
+}
+
+/// Format a time as an RFC 3339 UTC timestamp, to the second
+fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs());
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // The civil date of a day count since 1970-01-01, counting years from March so the leap day
+    // comes last
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let (year, month) = if month < 10 { (era * 400 + year_of_era, month + 3) } else { (era * 400 + year_of_era + 1, month - 9) };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use std::time::Duration;
+
+    fn bad_url() -> BadUrl {
+        BadUrl {
+            url: "https://example.com/missing/".to_string(),
+            found_on: vec!["https://example.com/".to_string()],
+            status: Some(404),
+            error_kind: ErrorKind::HttpStatus,
+            reason: "bad http response: 404".to_string(),
+            link_text: None,
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH + Duration::from_secs(951_825_600)), "2000-02-29T12:00:00Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH + Duration::from_secs(1_445_412_000)), "2015-10-21T07:20:00Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH + Duration::from_secs(1_735_689_599)), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let report = BadUrlsReport::new(Some(&start_url), vec![bad_url()]);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["start_url"], "https://example.com/");
+        assert_eq!(json["tool_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["bad_urls"][0]["url"], "https://example.com/missing/");
+
+        let read: BadUrlsReport = serde_json::from_value(json).unwrap();
+        assert_eq!(read.schema_version, report.schema_version);
+        assert_eq!(read.start_url, report.start_url);
+        assert_eq!(read.generated_at, report.generated_at);
+        assert_eq!(read.tool_version, report.tool_version);
+        assert_eq!(read.bad_urls.len(), 1);
+        assert_eq!(read.bad_urls[0].url, "https://example.com/missing/");
+        assert_eq!(read.bad_urls[0].found_on, vec!["https://example.com/"]);
+        assert_eq!(read.bad_urls[0].status, Some(404));
+        assert_eq!(read.bad_urls[0].error_kind, ErrorKind::HttpStatus);
+    }
+
+    #[test]
+    fn test_read_bad_urls() {
+        let path = std::env::temp_dir().join(format!("link-checker-bad-urls-{}.json", std::process::id()));
+        let read = |json: String| {
+            std::fs::write(&path, json).unwrap();
+            read_bad_urls(&path)
+        };
+
+        let report = BadUrlsReport::new(None, vec![bad_url()]);
+        assert_eq!(read(serde_json::to_string(&report).unwrap()).unwrap().len(), 1);
+        // The bare array written before the format was versioned
+        assert_eq!(read(serde_json::to_string(&vec![bad_url()]).unwrap()).unwrap().len(), 1);
+        let newer = BadUrlsReport { schema_version: SCHEMA_VERSION + 1, ..report };
+        assert!(read(serde_json::to_string(&newer).unwrap()).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+/// A set of strings in a fixed amount of memory, which may wrongly report a string as present
+/// (a false positive) but never misses one that was inserted. The chance of a false positive
+/// stays near the rate it was sized for until more strings than its capacity are inserted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    /// How many bits each string sets
+    hashes: u32,
+    /// How many strings were inserted that weren't already present
+    len: usize,
+}
+
+/// Spread the bits of a hash, so that similar strings set unrelated bits
+fn mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// FNV-1a, which unlike the standard library's hasher is the same in every release, so a saved
+/// filter still works after upgrading
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+impl BloomFilter {
+    /// A filter for about `capacity` strings with this chance of a false positive, e.g. `0.001`
+    pub fn new(capacity: usize, false_positive_rate: f64) -> BloomFilter {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-capacity * rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bit_count / capacity * ln2).round().clamp(1.0, 32.0) as u32;
+        BloomFilter { bits: vec![0; (bit_count / 64.0).ceil() as usize], hashes, len: 0 }
+    }
+
+    /// The bit positions of a string, by double hashing
+    fn positions(&self, text: &str) -> impl Iterator<Item = usize> + '_ {
+        let first = mix(fnv1a(text));
+        let step = mix(first) | 1;
+        let bit_count = self.bits.len() as u64 * 64;
+        (0..u64::from(self.hashes)).map(move |index| (first.wrapping_add(index.wrapping_mul(step)) % bit_count) as usize)
+    }
+
+    /// Whether the string was inserted, or is a false positive
+    pub fn contains(&self, text: &str) -> bool {
+        self.positions(text).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Insert the string, returning false if it was already present, or seemed to be
+    pub fn insert(&mut self, text: &str) -> bool {
+        let positions: Vec<usize> = self.positions(text).collect();
+        let mut added = false;
+        for bit in positions {
+            let mask = 1 << (bit % 64);
+            added |= self.bits[bit / 64] & mask == 0;
+            self.bits[bit / 64] |= mask;
+        }
+        if added {
+            self.len += 1;
+        }
+        added
+    }
+
+    /// The number of strings inserted, not counting those that seemed to be present already
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The memory used by the filter's bits
+    pub fn size_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_contains() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        assert!(filter.is_empty());
+        assert!(filter.insert("https://example.com/"));
+        assert!(!filter.insert("https://example.com/"));
+        assert!(filter.contains("https://example.com/"));
+        assert!(!filter.contains("https://example.com/other/"));
+        assert_eq!(filter.len(), 1);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        // About 9.6 bits per string for a 1% rate
+        assert!(filter.size_in_bytes() <= 12_000 + 8);
+        // A page that collides with the ones before it seems to be present already
+        let added = (0..10_000).filter(|page| filter.insert(&format!("https://example.com/page/{}/", page))).count();
+        assert!(added > 9_900, "{} added", added);
+        // Every inserted string is found
+        assert!((0..10_000).all(|page| filter.contains(&format!("https://example.com/page/{}/", page))));
+        let false_positives = (0..10_000).filter(|page| filter.contains(&format!("https://example.com/other/{}/", page))).count();
+        assert!(false_positives < 200, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut filter = BloomFilter::new(100, 0.001);
+        filter.insert("https://example.com/");
+        let read: BloomFilter = serde_json::from_str(&serde_json::to_string(&filter).unwrap()).unwrap();
+        assert!(read.contains("https://example.com/"));
+        assert!(!read.contains("https://example.com/other/"));
+        assert_eq!(read.len(), 1);
+    }
+}
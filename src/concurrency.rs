@@ -0,0 +1,163 @@
+use std::sync::{Condvar, Mutex};
+
+use log::{debug, info};
+
+struct State {
+    /// How many requests may be in flight at once
+    limit: usize,
+    in_use: usize,
+    /// Requests that succeeded since the last error or change of the limit
+    clean_streak: usize,
+    /// Requests that were already in flight when the limit was lowered, whose errors don't
+    /// lower it again since they were caused by the old concurrency
+    cooldown: usize,
+}
+
+/// Limits how many requests are in flight at once, adapting to how the server copes. The limit
+/// starts at the maximum and is halved when a request is throttled or fails with a server error,
+/// then raised by one after each stretch of as many successful requests as the limit.
+pub struct AdaptiveLimit {
+    max: usize,
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+/// Permission to make a request, given back when dropped
+pub struct Permit<'a> {
+    limit: &'a AdaptiveLimit,
+}
+
+impl AdaptiveLimit {
+    pub fn new(max: usize) -> AdaptiveLimit {
+        let max = max.max(1);
+        AdaptiveLimit {
+            max,
+            state: Mutex::new(State { limit: max, in_use: 0, clean_streak: 0, cooldown: 0 }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// The current number of requests allowed in flight
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Block until fewer requests than the limit are in flight
+    pub fn acquire(&self) -> Permit<'_> {
+        let mut state = self.state.lock().unwrap();
+        while state.in_use >= state.limit {
+            state = self.available.wait(state).unwrap();
+        }
+        state.in_use += 1;
+        Permit { limit: self }
+    }
+
+    /// Record the outcome of a request made with a permit that is still held
+    pub fn record(&self, overloaded: bool) {
+        let mut state = self.state.lock().unwrap();
+        let during_cooldown = state.cooldown > 0;
+        state.cooldown = state.cooldown.saturating_sub(1);
+        if overloaded {
+            state.clean_streak = 0;
+            if !during_cooldown && state.limit > 1 {
+                state.limit = (state.limit / 2).max(1);
+                // The other requests in flight were started before lowering the limit
+                state.cooldown = state.in_use.saturating_sub(1);
+                info!("Server is struggling, lowering concurrency to {}", state.limit);
+            }
+            return;
+        }
+        state.clean_streak += 1;
+        if state.clean_streak >= state.limit && state.limit < self.max {
+            state.limit += 1;
+            state.clean_streak = 0;
+            debug!("Raising concurrency to {}", state.limit);
+            self.available.notify_one();
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limit.state.lock().unwrap().in_use -= 1;
+        self.limit.available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_backs_off_and_ramps_up() {
+        let limit = AdaptiveLimit::new(8);
+        assert_eq!(limit.limit(), 8);
+
+        let permit = limit.acquire();
+        limit.record(true);
+        assert_eq!(limit.limit(), 4);
+        limit.record(true);
+        assert_eq!(limit.limit(), 2);
+        limit.record(true);
+        limit.record(true);
+        assert_eq!(limit.limit(), 1);
+
+        // Each raise takes as many clean requests as the current limit
+        limit.record(false);
+        assert_eq!(limit.limit(), 2);
+        limit.record(false);
+        assert_eq!(limit.limit(), 2);
+        limit.record(false);
+        assert_eq!(limit.limit(), 3);
+        for _ in 0..100 {
+            limit.record(false);
+        }
+        assert_eq!(limit.limit(), 8);
+        drop(permit);
+    }
+
+    #[test]
+    fn test_errors_in_flight_lower_the_limit_once() {
+        let limit = AdaptiveLimit::new(8);
+        let permits: Vec<Permit> = (0..4).map(|_| limit.acquire()).collect();
+
+        // The other three requests were already in flight when the first error came back
+        limit.record(true);
+        limit.record(true);
+        limit.record(true);
+        limit.record(true);
+        assert_eq!(limit.limit(), 4);
+        // A request made after lowering the limit lowers it again
+        limit.record(true);
+        assert_eq!(limit.limit(), 2);
+        drop(permits);
+    }
+
+    #[test]
+    fn test_acquire_waits_for_a_permit() {
+        let limit = Arc::new(AdaptiveLimit::new(2));
+        let permit = limit.acquire();
+        limit.record(true);
+        assert_eq!(limit.limit(), 1);
+
+        let acquired = Arc::new(AtomicUsize::new(0));
+        let waiter = {
+            let limit = limit.clone();
+            let acquired = acquired.clone();
+            thread::spawn(move || {
+                let _permit = limit.acquire();
+                acquired.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(acquired.load(Ordering::SeqCst), 0);
+
+        drop(permit);
+        waiter.join().unwrap();
+        assert_eq!(acquired.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use url::Url;
+
+/// Cookies shared by all crawler threads. Like credentials, cookies are only ever
+/// sent to the start URL's host, and only `Set-Cookie` headers from that host are stored.
+/// Cookie attributes such as `Path` and `Domain` are ignored, except that an expired
+/// cookie (`Max-Age=0`) is removed.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    host: Option<String>,
+    cookies: Mutex<BTreeMap<String, String>>,
+}
+
+impl CookieJar {
+    /// Create an empty cookie jar scoped to the host of the start URL
+    pub fn new(start_url: &Url) -> CookieJar {
+        CookieJar {
+            host: start_url.host_str().map(str::to_string),
+            cookies: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Add a cookie from a `name=value` string
+    pub fn with_cookie(self, cookie: &str) -> Result<CookieJar, String> {
+        let Some((name, value)) = cookie.split_once('=') else {
+            return Err(format!("expected name=value, got {:?}", cookie));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("missing cookie name in {:?}", cookie));
+        }
+        self.cookies.lock().unwrap().insert(name.to_string(), value.trim().to_string());
+        Ok(self)
+    }
+
+    /// The `Cookie` header value to send with a request for the given URL, if any
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        if !self.is_start_host(url) {
+            return None;
+        }
+        let cookies = self.cookies.lock().unwrap();
+        if cookies.is_empty() {
+            return None;
+        }
+        let pairs: Vec<String> = cookies.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Store a cookie from a `Set-Cookie` header received from the given URL
+    pub fn store(&self, url: &Url, set_cookie: &str) {
+        if !self.is_start_host(url) {
+            return;
+        }
+        let mut parts = set_cookie.split(';');
+        let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+            return;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+
+        let expired = parts.any(|attribute| {
+            attribute
+                .split_once('=')
+                .is_some_and(|(key, value)| key.trim().eq_ignore_ascii_case("max-age") && value.trim().starts_with(['0', '-']))
+        });
+        let mut cookies = self.cookies.lock().unwrap();
+        if expired {
+            cookies.remove(name);
+        } else {
+            cookies.insert(name.to_string(), value.trim().to_string());
+        }
+    }
+
+    /// Whether the URL is on the start URL's host, so its cookies are stored and sent
+    pub(crate) fn is_start_host(&self, url: &Url) -> bool {
+        self.host.is_some() && url.host_str() == self.host.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_cookies() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let jar = CookieJar::new(&start_url)
+            .with_cookie("session=abc123")
+            .unwrap()
+            .with_cookie("theme = dark")
+            .unwrap();
+
+        assert_eq!(jar.header_for(&start_url).as_deref(), Some("session=abc123; theme=dark"));
+        assert!(CookieJar::new(&start_url).with_cookie("no-separator").is_err());
+        assert!(CookieJar::new(&start_url).with_cookie("=value").is_err());
+        assert_eq!(CookieJar::new(&start_url).header_for(&start_url), None);
+    }
+
+    #[test]
+    fn test_set_cookie_updates_jar() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let jar = CookieJar::new(&start_url).with_cookie("session=old").unwrap();
+
+        jar.store(&start_url, "session=new; Path=/; HttpOnly; Secure");
+        jar.store(&start_url, "csrf=token; Max-Age=3600");
+        assert_eq!(jar.header_for(&start_url).as_deref(), Some("csrf=token; session=new"));
+
+        jar.store(&start_url, "csrf=; Max-Age=0");
+        assert_eq!(jar.header_for(&start_url).as_deref(), Some("session=new"));
+    }
+
+    #[test]
+    fn test_cookies_not_shared_with_other_domains() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let jar = CookieJar::new(&start_url).with_cookie("session=secret").unwrap();
+
+        let other = Url::parse("https://other.com/").unwrap();
+        assert_eq!(jar.header_for(&other), None);
+        assert_eq!(jar.header_for(&Url::parse("https://sub.example.com/").unwrap()), None);
+
+        // Other sites can't set cookies that are then sent to the start host
+        jar.store(&other, "tracker=1");
+        assert_eq!(jar.header_for(&start_url).as_deref(), Some("session=secret"));
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::{escape_csv, UrlResults};
+
+/// Write a CSV report with one row per checked URL
+pub fn write_csv(url_results: &UrlResults, mut out: impl Write) -> std::io::Result<()> {
+    writeln!(out, "url,source_page,status_code,content_type,bytes,result")?;
+    for checked_url in &url_results.checked_urls {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            escape_csv(&checked_url.url),
+            escape_csv(checked_url.source_page.as_deref().unwrap_or_default()),
+            checked_url.status.map(|status| status.to_string()).unwrap_or_default(),
+            escape_csv(checked_url.content_type.as_deref().unwrap_or_default()),
+            checked_url.bytes.map(|bytes| bytes.to_string()).unwrap_or_default(),
+            if checked_url.ok { "ok" } else { "broken" }
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the CSV report of the crawl to this path
+pub fn write_csv_report(url_results: &UrlResults, path: &str) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_csv(url_results, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckedUrl;
+
+    #[test]
+    fn test_write_csv() {
+        let checked_url = |url: &str, source_page: Option<&str>, status: Option<u16>, content_type: Option<&str>, bytes: Option<usize>| CheckedUrl {
+            url: url.to_string(),
+            source_page: source_page.map(str::to_string),
+            status,
+            content_type: content_type.map(str::to_string),
+            bytes,
+            duration_ms: None,
+            content_hash: None,
+            ok: status.is_some_and(|status| status < 400),
+        };
+        let url_results = UrlResults {
+            checked_urls: vec![
+                checked_url("https://example.com/", None, Some(200), Some("text/html; charset=utf-8"), Some(1234)),
+                checked_url("https://example.com/search?q=a,b", Some("https://example.com/"), Some(404), Some("text/html"), None),
+                checked_url("https://gone.example.org/", Some("https://example.com/"), None, None, None),
+            ],
+            ..UrlResults::default()
+        };
+
+        let mut csv = Vec::new();
+        write_csv(&url_results, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "url,source_page,status_code,content_type,bytes,result\n\
+             https://example.com/,,200,text/html; charset=utf-8,1234,ok\n\
+             \"https://example.com/search?q=a,b\",https://example.com/,404,text/html,,broken\n\
+             https://gone.example.org/,https://example.com/,,,,broken\n"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::UrlResults;
+
+/// Quote a URL as a DOT identifier
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Every page and link target of the crawl, in breadth-first order from the pages that no
+/// other page links to, such as the start URL. Pages that are only reachable through a cycle
+/// come after, in sorted order.
+fn nodes_in_crawl_order(edges: &BTreeMap<&str, BTreeSet<&str>>) -> Vec<String> {
+    let linked: HashSet<&str> = edges.values().flatten().copied().collect();
+    let roots = edges.keys().copied().filter(|page| !linked.contains(page));
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut order = Vec::new();
+    for root in roots.chain(edges.keys().copied()) {
+        if !seen.insert(root) {
+            continue;
+        }
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node.to_string());
+            for &target in edges.get(node).into_iter().flatten() {
+                if seen.insert(target) {
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Render the crawl as a GraphViz DOT graph: each page and link target is a node, and each link
+/// an edge from the page it was found on. Broken links and their edges are red.
+pub fn to_dot(results: &UrlResults) -> String {
+    to_dot_with_max_nodes(results, None)
+}
+
+/// Like `to_dot`, but with at most `max_nodes` nodes, so that the graph of a large site can still
+/// be rendered. The nodes closest to the start of the crawl are kept, with only the links between
+/// them, and the graph's label says how much was left out.
+pub fn to_dot_with_max_nodes(results: &UrlResults, max_nodes: Option<usize>) -> String {
+    let edges: BTreeMap<&str, BTreeSet<&str>> = results
+        .url_map
+        .iter()
+        .map(|(page, links)| (page.as_str(), links.iter().map(String::as_str).collect()))
+        .collect();
+    let broken: HashSet<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+
+    let mut nodes = nodes_in_crawl_order(&edges);
+    let total_nodes = nodes.len();
+    if let Some(max_nodes) = max_nodes {
+        nodes.truncate(max_nodes);
+    }
+    let included: HashSet<&str> = nodes.iter().map(String::as_str).collect();
+
+    let mut dot = String::from("digraph crawl {\n    rankdir=LR;\n    node [shape=box, fontsize=10];\n");
+    if nodes.len() < total_nodes {
+        let _ = writeln!(dot, "    label={};\n    labelloc=t;", quote(&format!("Showing {} of {} pages and links", nodes.len(), total_nodes)));
+    }
+    for node in &nodes {
+        if broken.contains(node.as_str()) {
+            let _ = writeln!(dot, "    {} [color=red, fontcolor=red];", quote(node));
+        } else {
+            let _ = writeln!(dot, "    {};", quote(node));
+        }
+    }
+    for (page, links) in &edges {
+        if !included.contains(page) {
+            continue;
+        }
+        for link in links.iter().filter(|link| included.contains(*link)) {
+            let style = if broken.contains(link) { " [color=red]" } else { "" };
+            let _ = writeln!(dot, "    {} -> {}{};", quote(page), quote(link), style);
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Write the crawl graph to this path as a GraphViz DOT file
+pub fn write_dot(results: &UrlResults, max_nodes: Option<usize>, path: &Path) -> std::io::Result<()> {
+    fs::write(path, to_dot_with_max_nodes(results, max_nodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BadUrl, ErrorKind};
+
+    fn results() -> UrlResults {
+        let page = |page: &str, links: &[&str]| (page.to_string(), links.iter().map(|link| link.to_string()).collect());
+        UrlResults {
+            url_map: [
+                page("https://example.com/", &["https://example.com/a/", "https://example.com/b/"]),
+                page("https://example.com/a/", &["https://example.com/", "https://example.com/gone\"quoted\""]),
+                page("https://example.com/b/", &["https://example.com/a/deep/"]),
+                page("https://example.com/a/deep/", &[]),
+            ]
+            .into(),
+            bad_urls: vec![BadUrl {
+                url: "https://example.com/gone\"quoted\"".to_string(),
+                found_on: vec!["https://example.com/a/".to_string()],
+                status: Some(404),
+                error_kind: ErrorKind::HttpStatus,
+                reason: "bad http response: 404".to_string(),
+                link_text: None,
+            }],
+            ..UrlResults::default()
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = to_dot(&results());
+        assert!(dot.starts_with("digraph crawl {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    \"https://example.com/\" -> \"https://example.com/a/\";\n"));
+        assert!(dot.contains("    \"https://example.com/a/\" -> \"https://example.com/\";\n"));
+        assert!(dot.contains(r#"    "https://example.com/gone\"quoted\"" [color=red, fontcolor=red];"#));
+        assert!(dot.contains(r#"    "https://example.com/a/" -> "https://example.com/gone\"quoted\"" [color=red];"#));
+        assert_eq!(dot.matches(" -> ").count(), 5);
+        assert!(!dot.contains("label="));
+    }
+
+    #[test]
+    fn test_to_dot_with_max_nodes() {
+        // Every page is linked from another, so the first page in sorted order is the root
+        let dot = to_dot_with_max_nodes(&results(), Some(3));
+        assert!(dot.contains("label=\"Showing 3 of 5 pages and links\";"));
+        assert!(dot.contains("    \"https://example.com/\";\n"));
+        assert!(dot.contains("    \"https://example.com/a/\";\n"));
+        assert!(dot.contains("    \"https://example.com/b/\";\n"));
+        assert!(!dot.contains("deep"));
+        assert!(!dot.contains("gone"));
+        assert_eq!(dot.matches(" -> ").count(), 3);
+
+        assert_eq!(to_dot_with_max_nodes(&results(), Some(100)), to_dot(&results()));
+    }
+}
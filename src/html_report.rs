@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{escape_xml, format_bytes, BadUrl, CrawlStats, UrlResults};
+
+/// Inline styles, so the report works offline and when attached to an email
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+h1 { margin-bottom: 0.25rem; }
+.interrupted { color: #a15c00; }
+.stats { display: flex; flex-wrap: wrap; gap: 1rem; margin: 1.5rem 0; }
+.stat { border: 1px solid #ddd; border-radius: 6px; padding: 0.75rem 1rem; min-width: 8rem; }
+.stat .value { font-size: 1.5rem; font-weight: bold; }
+.stat.broken .value { color: #b00020; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.4rem 0.6rem; text-align: left; vertical-align: top; }
+th { cursor: pointer; background: #f5f5f5; user-select: none; }
+th::after { content: " \2195"; color: #999; }
+td { word-break: break-all; }
+ul { margin: 0; padding-left: 1.2rem; }
+"#;
+
+/// Sorts the table by a column when its header is clicked, toggling the order on each click
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach(function (header, column) {
+  header.addEventListener("click", function () {
+    var body = header.closest("table").tBodies[0];
+    var ascending = header.dataset.order !== "asc";
+    header.dataset.order = ascending ? "asc" : "desc";
+    Array.from(body.rows)
+      .sort(function (a, b) {
+        var order = a.cells[column].textContent.localeCompare(b.cells[column].textContent, undefined, { numeric: true });
+        return ascending ? order : -order;
+      })
+      .forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+fn stat(label: &str, value: &str, class: &str) -> String {
+    format!(
+        r#"<div class="stat {}"><div class="value">{}</div><div>{}</div></div>"#,
+        class,
+        escape_xml(value),
+        escape_xml(label)
+    )
+}
+
+fn link(url: &str) -> String {
+    format!(r#"<a href="{0}">{0}</a>"#, escape_xml(url))
+}
+
+fn broken_link_row(bad_url: &BadUrl) -> String {
+    let status = bad_url.status.map_or_else(|| bad_url.reason.clone(), |status| status.to_string());
+    let error_kind = serde_json::to_value(bad_url.error_kind)
+        .ok()
+        .and_then(|kind| kind.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let found_on = if bad_url.found_on.is_empty() {
+        "(starting URL)".to_string()
+    } else {
+        let pages: String = bad_url.found_on.iter().map(|page| format!("<li>{}</li>", link(page))).collect();
+        format!("<ul>{}</ul>", pages)
+    };
+    format!(
+        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        link(&bad_url.url),
+        escape_xml(&status),
+        error_kind,
+        found_on
+    )
+}
+
+/// Render a self-contained HTML page summarizing the crawl: its totals and a sortable table of
+/// the broken links with where they were found
+pub fn render_report(results: &UrlResults, stats: &CrawlStats) -> String {
+    let interrupted = if stats.interrupted {
+        r#"<p class="interrupted">The crawl was interrupted, so these results are partial.</p>"#
+    } else {
+        ""
+    };
+    let stats_html = [
+        stat("pages crawled", &stats.pages_crawled.to_string(), ""),
+        stat("unique URLs", &stats.unique_urls.to_string(), ""),
+        stat("broken links", &stats.broken_links.to_string(), if stats.broken_links > 0 { "broken" } else { "" }),
+        stat("downloaded", &format_bytes(stats.bytes_downloaded), ""),
+        stat("elapsed", &format!("{:.1}s", stats.elapsed_ms as f64 / 1000.0), ""),
+    ]
+    .join("\n");
+
+    let broken_links = if results.bad_urls.is_empty() {
+        "<p>No broken links found.</p>".to_string()
+    } else {
+        let rows: String = results.bad_urls.iter().map(broken_link_row).collect();
+        format!(
+            "<table>\n<thead><tr><th>URL</th><th>Status</th><th>Error</th><th>Found on</th></tr></thead>\n<tbody>\n{}</tbody>\n</table>",
+            rows
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Link check report</title>
+<style>{}</style>
+</head>
+<body>
+<h1>Link check report</h1>
+{}
+<div class="stats">
+{}
+</div>
+<h2>Broken links</h2>
+{}
+<script>{}</script>
+</body>
+</html>
+"#,
+        STYLE, interrupted, stats_html, broken_links, SORT_SCRIPT
+    )
+}
+
+/// Write the HTML report of the crawl to this path
+pub fn write_html_report(results: &UrlResults, stats: &CrawlStats, path: &Path) -> std::io::Result<()> {
+    fs::write(path, render_report(results, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_render_report() {
+        let results = UrlResults {
+            bad_urls: vec![
+                BadUrl {
+                    url: "https://example.com/missing?a=1&b=<2>".to_string(),
+                    found_on: vec!["https://example.com/".to_string()],
+                    status: Some(404),
+                    error_kind: ErrorKind::HttpStatus,
+                    reason: "bad http response: 404".to_string(),
+                    link_text: None,
+                },
+                BadUrl {
+                    url: "https://gone.example.org/".to_string(),
+                    found_on: Vec::new(),
+                    status: None,
+                    error_kind: ErrorKind::Dns,
+                    reason: "dns error: host not found".to_string(),
+                    link_text: None,
+                },
+            ],
+            ..UrlResults::default()
+        };
+        let stats = CrawlStats {
+            pages_crawled: 12,
+            unique_urls: 40,
+            broken_links: 2,
+            total_requests: 52,
+            head_requests: 28,
+            get_requests: 24,
+            retries: 1,
+            bytes_downloaded: 2048,
+            estimated_bytes: 0,
+            latency: None,
+            elapsed_ms: 1500,
+            interrupted: false,
+        };
+
+        let html = render_report(&results, &stats);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(r#"<div class="value">12</div><div>pages crawled</div>"#));
+        assert!(html.contains(r#"<div class="value">2.0 KB</div>"#));
+        assert!(html.contains(r#"<div class="value">1.5s</div>"#));
+        assert!(html.contains("https://example.com/missing?a=1&amp;b=&lt;2&gt;"));
+        assert!(html.contains("<td>404</td><td>http_status</td>"));
+        assert!(html.contains("<td>dns error: host not found</td><td>dns</td><td>(starting URL)</td>"));
+        assert!(!html.contains("interrupted, so"));
+        // Everything is inline, so the report works offline
+        assert!(!html.contains("<link"));
+        assert!(!html.contains("src="));
+
+        let empty = render_report(&UrlResults::default(), &CrawlStats { interrupted: true, broken_links: 0, ..stats });
+        assert!(empty.contains("No broken links found."));
+        assert!(empty.contains("The crawl was interrupted"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::{escape_xml, BadUrl, UrlResults};
+
+/// Write a JUnit XML report where each crawled page is a test case
+/// and each broken link found on it is a failure
+pub fn write_junit(url_results: &UrlResults, mut out: impl Write) -> std::io::Result<()> {
+    // Group broken links by the page they were found on; a broken start URL is its own test case
+    let mut test_cases: BTreeMap<&str, Vec<&BadUrl>> = url_results
+        .url_map
+        .keys()
+        .map(|page| (page.as_str(), Vec::new()))
+        .collect();
+    test_cases.extend(url_results.broken_by_source());
+
+    // A broken link found on several pages fails each of their test cases
+    let failure_count = test_cases.values().filter(|bad_urls| !bad_urls.is_empty()).count();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, "<testsuites>")?;
+    writeln!(
+        out,
+        r#"  <testsuite name="link-checker" tests="{}" failures="{}">"#,
+        test_cases.len(),
+        failure_count
+    )?;
+    for (page, bad_urls) in test_cases {
+        let page = escape_xml(page);
+        if bad_urls.is_empty() {
+            writeln!(out, r#"    <testcase classname="link-checker" name="{page}"/>"#)?;
+            continue;
+        }
+        writeln!(out, r#"    <testcase classname="link-checker" name="{page}">"#)?;
+        for bad_url in bad_urls {
+            let message = escape_xml(&format!("{}: {}", bad_url.url, bad_url.reason));
+            writeln!(out, r#"      <failure message="{message}">{message}</failure>"#)?;
+        }
+        writeln!(out, "    </testcase>")?;
+    }
+    writeln!(out, "  </testsuite>")?;
+    writeln!(out, "</testsuites>")?;
+    Ok(())
+}
+
+/// Write the JUnit XML report of the crawl to this path
+pub fn write_junit_report(url_results: &UrlResults, path: &str) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_junit(url_results, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_junit_report() {
+        let bad_url = |url: &str, found_on: &[&str]| BadUrl {
+            url: url.to_string(),
+            found_on: found_on.iter().map(|page| page.to_string()).collect(),
+            status: Some(404),
+            error_kind: ErrorKind::HttpStatus,
+            reason: "bad http response: 404".to_string(),
+            link_text: None,
+        };
+        let url_results = UrlResults {
+            bad_urls: vec![
+                bad_url("https://example.com/missing?a=1&b=<2>", &["https://example.com/", "https://example.com/about/", "https://example.com/blog/"]),
+                bad_url("https://example.com/old/", &["https://example.com/"]),
+                bad_url("https://example.com/gone/", &[]),
+            ],
+            url_map: ["https://example.com/", "https://example.com/about/", "https://example.com/blog/", "https://example.com/docs/"]
+                .into_iter()
+                .map(|page| (page.to_string(), Vec::new()))
+                .collect(),
+            ..UrlResults::default()
+        };
+
+        let mut xml = Vec::new();
+        write_junit(&url_results, &mut xml).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        // The link broken on three pages fails three test cases, and the broken start URL is its own.
+        // The start page fails once, with both of its broken links.
+        assert!(xml.contains(r#"<testsuite name="link-checker" tests="5" failures="4">"#), "{}", xml);
+        assert_eq!(xml.matches("<failure ").count(), 5);
+        assert!(xml.contains(r#"<testcase classname="link-checker" name="https://example.com/docs/"/>"#));
+        assert!(xml.contains(
+            r#"    <testcase classname="link-checker" name="https://example.com/gone/">
+      <failure message="https://example.com/gone/: bad http response: 404">"#
+        ));
+        assert!(xml.contains(r#"<failure message="https://example.com/missing?a=1&amp;b=&lt;2&gt;: bad http response: 404">"#));
+        assert!(!xml.contains("<2>"));
+        assert!(xml.trim_end().ends_with("</testsuites>"));
+    }
+}
@@ -1,42 +1,186 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::bloom::BloomFilter;
+
+pub mod auth;
+pub mod bad_urls;
+pub mod bloom;
+pub mod concurrency;
+pub mod cookies;
+pub mod csv_report;
+mod crawler;
+pub mod graph;
+pub mod html_report;
+pub mod junit;
+pub mod login;
+pub mod markdown;
+pub mod rate_limit;
+pub mod robots;
+pub mod sarif;
+pub mod sitemap;
+pub mod state;
+
+pub use crawler::{extract_links, BadUrl, BrokenLinkChanges, CheckedUrl, ContentTypeMismatch, CrawlProgress, CrawlStats, ErrorKind, LatencyStats, LinkChecker, MetaRefresh, MixedContent, RedirectHop, RedirectedUrl, RequestCounts, ThreadMetrics, UrlResults};
+
+/// The User-Agent sent with every request unless overridden
+pub const DEFAULT_USER_AGENT: &str = concat!("link-checker/", env!("CARGO_PKG_VERSION"));
+
 pub fn is_absolute_url(href: &str) -> bool {
     // Check if URL has a scheme (protocol) like http://, https://, ftp://, mailto:, etc.
     href.contains("://") || href.starts_with("mailto:") || href.starts_with("tel:")
 }
 
+static CSS_URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"url\(\s*['"]?([^'")]+?)['"]?\s*\)"#).unwrap());
+
+/// Extract the references of all `url(...)` expressions in a CSS snippet,
+/// skipping inline `data:` URIs
+pub fn extract_css_urls(css: &str) -> Vec<&str> {
+    CSS_URL_REGEX
+        .captures_iter(css)
+        .filter_map(|captures| captures.get(1))
+        .map(|reference| reference.as_str())
+        .filter(|reference| !reference.starts_with("data:"))
+        .collect()
+}
+
+/// Extract the candidate URLs of an `srcset` attribute such as `"img-480.jpg 480w, img-960.jpg 960w"`,
+/// dropping the width or density descriptors and skipping inline `data:` URIs.
+/// As in browsers, a URL ends at whitespace, so URLs may contain commas.
+pub fn extract_srcset_urls(srcset: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+        let end = rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len());
+        let (candidate, after) = rest.split_at(end);
+        // A comma right after the URL ends the candidate, otherwise the descriptors run up to the next comma
+        let url = candidate.trim_end_matches(',');
+        rest = if url.len() < candidate.len() {
+            after
+        } else {
+            after.find(',').map_or("", |comma| &after[comma + 1..])
+        };
+        if !url.starts_with("data:") {
+            urls.push(url);
+        }
+    }
+}
+
+static EMAIL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z0-9.!#$%&'*+/=?^_`{|}~-]+@[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?(?:\.[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?)+$").unwrap()
+});
+
+static PHONE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\+?[0-9]{3,15}$").unwrap());
+
+/// Check the syntax of a `mailto:` or `tel:` link: every address of a `mailto:` link must be
+/// a valid email address, and a `tel:` number must be 3 to 15 digits with an optional leading `+`,
+/// ignoring visual separators like spaces, dashes, dots and parentheses.
+/// Returns `None` for other links.
+pub fn validate_contact_link(href: &str) -> Option<bool> {
+    let (scheme, rest) = href.trim().split_once(':')?;
+    let rest = percent_decode_str(rest).decode_utf8_lossy();
+    if scheme.eq_ignore_ascii_case("mailto") {
+        // Several addresses are separated by commas, and headers like ?subject= follow them
+        let addresses = rest.split('?').next().unwrap_or_default();
+        if addresses.trim().is_empty() {
+            return Some(true);
+        }
+        Some(addresses.split(',').all(|address| EMAIL_REGEX.is_match(address.trim())))
+    } else if scheme.eq_ignore_ascii_case("tel") {
+        // Parameters like ;ext=123 follow the number
+        let number = rest.split(';').next().unwrap_or_default();
+        let digits: String = number.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')')).collect();
+        Some(PHONE_REGEX.is_match(&digits))
+    } else {
+        None
+    }
+}
+
 pub fn is_likely_html_content(url: &Url) -> bool {
-    let path = url.path().to_lowercase();
-    
-    // If no extension or ends with /, assume it's HTML
-    if path.is_empty() || path.ends_with('/') {
-        return true;
-    }
-    
-    // Check if the path contains a dot (indicating a file extension)
-    if let Some(last_segment) = path.split('/').last() {
-        if last_segment.contains('.') {
-            // Has an extension, check if it's HTML-like
-            if let Some(extension) = last_segment.split('.').last() {
-                matches!(extension, 
-                    "html" | "htm" | "php" | "asp" | "aspx" | "jsp" | "cfm" | "cgi" | "pl" | "py" | "rb"
-                )
+    HtmlDetection::default().is_likely_html(url)
+}
+
+/// Settings for guessing from its URL whether a link points to an HTML page.
+/// The defaults match `is_likely_html_content`.
+#[derive(Debug, Clone)]
+pub struct HtmlDetection {
+    extra_extensions: HashSet<String>,
+    extensionless_html: bool,
+}
+
+impl Default for HtmlDetection {
+    fn default() -> Self {
+        HtmlDetection {
+            extra_extensions: HashSet::new(),
+            extensionless_html: true,
+        }
+    }
+}
+
+impl HtmlDetection {
+    /// Also treat these file extensions as HTML, e.g. `.xhtml` or `shtml`
+    pub fn with_extensions<S: AsRef<str>>(mut self, extensions: impl IntoIterator<Item = S>) -> HtmlDetection {
+        self.extra_extensions.extend(
+            extensions
+                .into_iter()
+                .map(|extension| extension.as_ref().trim().trim_start_matches('.').to_lowercase())
+                .filter(|extension| !extension.is_empty()),
+        );
+        self
+    }
+
+    /// Whether paths without a file extension, like `/api/v1/users`, are assumed to be HTML
+    pub fn extensionless_html(mut self, extensionless_html: bool) -> HtmlDetection {
+        self.extensionless_html = extensionless_html;
+        self
+    }
+
+    pub fn is_likely_html(&self, url: &Url) -> bool {
+        let path = url.path().to_lowercase();
+
+        // If no extension or ends with /, assume it's HTML
+        if path.is_empty() || path.ends_with('/') {
+            return true;
+        }
+
+        // Check if the path contains a dot (indicating a file extension)
+        if let Some(last_segment) = path.rsplit('/').next() {
+            if last_segment.contains('.') {
+                // Has an extension, check if it's HTML-like
+                if let Some(extension) = last_segment.rsplit('.').next() {
+                    matches!(extension,
+                        "html" | "htm" | "php" | "asp" | "aspx" | "jsp" | "cfm" | "cgi" | "pl" | "py" | "rb"
+                    ) || self.extra_extensions.contains(extension)
+                } else {
+                    false
+                }
             } else {
-                false
+                // No extension in the last segment, likely HTML (like /api/v1/users or /page)
+                self.extensionless_html
             }
         } else {
-            // No extension in the last segment, likely HTML (like /api/v1/users or /page)
+            // Empty path, likely HTML
             true
         }
-    } else {
-        // Empty path, likely HTML
-        true
     }
 }
 
 pub fn format_bytes(bytes: usize) -> String {
-    if bytes >= 1_048_576 {
+    if bytes >= 1_099_511_627_776 {
+        format!("{:.1} TB", bytes as f64 / 1_099_511_627_776.0)
+    } else if bytes >= 1_073_741_824 {
+        format!("{:.1} GB", bytes as f64 / 1_073_741_824.0)
+    } else if bytes >= 1_048_576 {
         format!("{:.1} MB", bytes as f64 / 1_048_576.0)
     } else if bytes >= 1_024 {
         format!("{:.1} KB", bytes as f64 / 1_024.0)
@@ -45,6 +189,31 @@ pub fn format_bytes(bytes: usize) -> String {
     }
 }
 
+/// Escape the characters that are not allowed in XML text and attribute values
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quote a CSV field if it contains a comma, quote or line break
+pub fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
 /// Add a trailing slash to a URL if it doesn't have one and doesn't end with a file extension.
 /// This helps with relative link resolution.
 pub fn add_trailing_slash_if_needed(url: Url) -> Url {
@@ -56,7 +225,7 @@ pub fn add_trailing_slash_if_needed(url: Url) -> Url {
     }
 
     // Check if the last segment has an extension
-    if let Some(last_segment) = path.split('/').last() {
+    if let Some(last_segment) = path.rsplit('/').next() {
         if last_segment.contains('.') {
             // Has an extension, don't add trailing slash
             return url;
@@ -69,11 +238,80 @@ pub fn add_trailing_slash_if_needed(url: Url) -> Url {
     modified_url
 }
 
+/// How the visited set treats `/page` and `/page/`, which are otherwise different pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingSlash {
+    /// Treat `/page` as `/page/`, unless the last segment has a file extension like `/page.html`
+    Add,
+    /// Treat `/page/` as `/page`. The root path `/` keeps its slash
+    Remove,
+}
+
+impl TrailingSlash {
+    /// Apply this normalization to the path of a URL
+    pub fn normalize(self, url: Url) -> Url {
+        match self {
+            TrailingSlash::Add => add_trailing_slash_if_needed(url),
+            TrailingSlash::Remove => {
+                let path = url.path();
+                if path.len() <= 1 || !path.ends_with('/') {
+                    return url;
+                }
+                let mut modified_url = url.clone();
+                modified_url.set_path(path.trim_end_matches('/'));
+                modified_url
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "add" => Ok(TrailingSlash::Add),
+            "remove" => Ok(TrailingSlash::Remove),
+            _ => Err(format!("expected \"add\" or \"remove\", got {:?}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrawlState {
     domain: String,
     start_url_path: String,
+    /// More path prefixes in scope besides the start URL's
+    #[serde(default)]
+    path_prefixes: Vec<String>,
     domain_match: bool,
+    #[serde(default)]
+    include_subdomains: bool,
+    #[serde(default)]
+    scheme: String,
+    #[serde(default)]
+    same_scheme: bool,
+    ignore_query: bool,
+    #[serde(default)]
+    trailing_slash: Option<TrailingSlash>,
     visited_pages: HashSet<String>,
+    /// Replaces `visited_pages` to bound its memory on huge sites, at the cost of wrongly taking
+    /// a few pages as visited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visited_filter: Option<BloomFilter>,
+    /// Pages that declared a different canonical URL, mapped to that URL
+    #[serde(default)]
+    canonical_urls: HashMap<String, String>,
+}
+
+/// Whether a path is a path prefix or below it, on path segment boundaries: the prefix `/docs`
+/// includes `/docs` and `/docs/guide` but not `/docs-archive`, and `/docs/` also includes `/docs` itself
+fn is_under_path(path: &str, prefix: &str) -> bool {
+    if prefix.ends_with('/') {
+        return path.starts_with(prefix) || path == prefix.trim_end_matches('/');
+    }
+    path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
 }
 
 impl CrawlState {
@@ -82,31 +320,125 @@ impl CrawlState {
         let normalized_url = Self::normalize_url(start_url);
         visited_pages.insert(normalized_url);
         CrawlState {
-            domain: start_url.domain().unwrap().to_string(),
+            // The host may also be an IP address, like 192.168.1.10 or [::1]
+            domain: start_url.host_str().unwrap_or_default().to_string(),
             start_url_path: start_url.path().to_string(),
+            path_prefixes: Vec::new(),
             domain_match,
+            include_subdomains: false,
+            scheme: start_url.scheme().to_string(),
+            same_scheme: false,
+            ignore_query: false,
+            trailing_slash: None,
             visited_pages,
+            visited_filter: None,
+            canonical_urls: HashMap::new(),
         }
     }
 
+    /// Also consider subdomains of the start URL's domain in scope, so `docs.example.com` is
+    /// crawled when starting from `example.com`. A leading `www.` of the start domain is ignored
+    pub fn with_include_subdomains(mut self, include_subdomains: bool) -> CrawlState {
+        self.include_subdomains = include_subdomains;
+        self
+    }
+
+    /// Also consider URLs under these path prefixes in scope, matched like the start URL's path,
+    /// e.g. `/docs/` and `/blog/`. A prefix without a leading `/` gets one.
+    pub fn with_path_prefixes<S: AsRef<str>>(mut self, path_prefixes: impl IntoIterator<Item = S>) -> CrawlState {
+        self.path_prefixes.extend(path_prefixes.into_iter().map(|prefix| {
+            let prefix = prefix.as_ref().trim();
+            if prefix.starts_with('/') { prefix.to_string() } else { format!("/{}", prefix) }
+        }));
+        self
+    }
+
+    /// Only consider URLs with the start URL's scheme in scope, so `http://` links on an `https://` site aren't crawled
+    pub fn with_same_scheme(mut self, same_scheme: bool) -> CrawlState {
+        self.same_scheme = same_scheme;
+        self
+    }
+
+    /// Treat URLs that only differ in their query string as the same page
+    pub fn with_ignore_query(mut self, ignore_query: bool) -> CrawlState {
+        self.ignore_query = ignore_query;
+        self.rekey_visited_pages();
+        self
+    }
+
+    /// Treat URLs that only differ in a trailing slash as the same page
+    pub fn with_trailing_slash(mut self, trailing_slash: Option<TrailingSlash>) -> CrawlState {
+        self.trailing_slash = trailing_slash;
+        self.rekey_visited_pages();
+        self
+    }
+
+    /// Record visited pages in a Bloom filter sized for about `capacity` pages instead of a set,
+    /// so memory stays bounded however large the site is. A page whose key collides with visited
+    /// ones (at about this false positive rate) is skipped as if it had been visited already.
+    /// Set the normalization options first, since the filter's keys can't be recomputed.
+    pub fn with_bloom_filter(mut self, capacity: usize, false_positive_rate: f64) -> CrawlState {
+        let mut filter = BloomFilter::new(capacity, false_positive_rate);
+        for page in self.visited_pages.drain() {
+            filter.insert(&page);
+        }
+        self.visited_filter = Some(filter);
+        self
+    }
+
+    /// Recompute the keys of the visited set after the normalization options changed
+    fn rekey_visited_pages(&mut self) {
+        let visited_pages = std::mem::take(&mut self.visited_pages);
+        self.visited_pages = visited_pages
+            .iter()
+            .filter_map(|page| Url::parse(page).ok())
+            .map(|page| self.visited_key(&page))
+            .collect();
+    }
+
     /// Remove the fragment (hash) part of a URL to avoid treating
-    /// page.html#section1 and page.html#section2 as different pages
+    /// page.html#section1 and page.html#section2 as different pages,
+    /// and sort the query parameters so ?a=1&b=2 and ?b=2&a=1 are the same page.
+    /// The scheme and host are already lowercased when an `http(s)` URL is parsed,
+    /// while the path and query keep their case, which servers may treat as significant.
     pub fn normalize_url(url: &Url) -> String {
         let mut normalized = url.clone();
         normalized.set_fragment(None);
+
+        if normalized.query().is_some() {
+            let mut pairs: Vec<(String, String)> = normalized
+                .query_pairs()
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            pairs.sort();
+            if pairs.is_empty() {
+                normalized.set_query(None);
+            } else {
+                normalized.query_pairs_mut().clear().extend_pairs(pairs);
+            }
+        }
+
         normalized.to_string()
     }
 
+    /// The key a page is recorded under in the visited set
+    fn visited_key(&self, url: &Url) -> String {
+        let mut url = url.clone();
+        if self.ignore_query {
+            url.set_query(None);
+        }
+        if let Some(trailing_slash) = self.trailing_slash {
+            url = trailing_slash.normalize(url);
+        }
+        Self::normalize_url(&url)
+    }
+
     /// Determine whether a URL should be visited at all.
     /// By default, only visit URLs with the same path prefix.
     /// If domain_match is enabled, visit any URL in the same domain.
     pub fn should_visit_url(&self, url: &Url) -> bool {
-        let Some(url_domain) = url.domain() else {
-            return false;
-        };
-        
-        // Must be in the same domain
-        if url_domain != self.domain {
+        // Must be in the same domain, and with the same scheme if required
+        if !self.is_in_domain(url) || !self.is_in_scheme(url) {
             return false;
         }
         
@@ -115,8 +447,35 @@ impl CrawlState {
             return true;
         }
         
-        // Otherwise, check if the URL path starts with the same prefix as the start URL
-        url.path().starts_with(&self.start_url_path)
+        // Otherwise, the URL must be under the start URL's path or another path prefix
+        let path = url.path();
+        is_under_path(path, &self.start_url_path) || self.path_prefixes.iter().any(|prefix| is_under_path(path, prefix))
+    }
+
+    /// Whether the URL's host is the start URL's host, compared case-insensitively,
+    /// or one of its subdomains if those are included. Like the scheme, the port isn't compared.
+    pub fn is_in_domain(&self, url: &Url) -> bool {
+        let Some(url_host) = url.host_str() else {
+            return false;
+        };
+        if url_host.eq_ignore_ascii_case(&self.domain) {
+            return true;
+        }
+        // IP addresses have no subdomains
+        let Some(url_domain) = url.domain().filter(|_| self.include_subdomains) else {
+            return false;
+        };
+
+        let base_domain = self.domain.strip_prefix("www.").unwrap_or(&self.domain);
+        let url_domain = url_domain.to_ascii_lowercase();
+        let base_domain = base_domain.to_ascii_lowercase();
+        url_domain == base_domain
+            || url_domain.strip_suffix(&base_domain).is_some_and(|subdomain| subdomain.ends_with('.'))
+    }
+
+    /// Whether the URL has the start URL's scheme, or any scheme unless the scheme must match
+    pub fn is_in_scheme(&self, url: &Url) -> bool {
+        !self.same_scheme || url.scheme() == self.scheme
     }
 
     /// Determine whether links within the given page should be extracted.
@@ -127,11 +486,50 @@ impl CrawlState {
         self.should_visit_url(url)
     }
 
+    /// The normalized URLs of every page visited so far, which is empty if they are kept in a
+    /// Bloom filter instead
+    pub fn visited_pages(&self) -> &HashSet<String> {
+        &self.visited_pages
+    }
+
+    /// The number of pages visited so far
+    pub fn visited_count(&self) -> usize {
+        self.visited_filter.as_ref().map_or(self.visited_pages.len(), BloomFilter::len)
+    }
+
+    fn is_visited(&self, key: &str) -> bool {
+        match &self.visited_filter {
+            Some(filter) => filter.contains(key),
+            None => self.visited_pages.contains(key),
+        }
+    }
+
+    /// Pages that declared a different canonical URL, mapped to that URL
+    pub fn canonical_urls(&self) -> &HashMap<String, String> {
+        &self.canonical_urls
+    }
+
+    /// Record the canonical URL a page declared, returning true if it is a different page
+    /// that has already been visited, which makes this page a duplicate
+    pub fn record_canonical(&mut self, page: &Url, canonical: &Url) -> bool {
+        let page_key = self.visited_key(page);
+        let canonical_key = self.visited_key(canonical);
+        if page_key == canonical_key {
+            return false;
+        }
+        let already_visited = self.is_visited(&canonical_key);
+        self.canonical_urls.insert(page_key, canonical_key);
+        already_visited
+    }
+
     /// Mark the given page as visited, returning false if it had already
     /// been visited. Uses normalized URL (without fragment) for comparison.
     pub fn mark_visited(&mut self, url: &Url) -> bool {
-        let normalized_url = Self::normalize_url(url);
-        self.visited_pages.insert(normalized_url)
+        let normalized_url = self.visited_key(url);
+        match &mut self.visited_filter {
+            Some(filter) => filter.insert(&normalized_url),
+            None => self.visited_pages.insert(normalized_url),
+        }
     }
 }
 
@@ -171,6 +569,56 @@ mod tests {
         assert!(!crawl_state.should_extract_links(&url6));
     }
 
+    #[test]
+    fn test_path_prefix_segment_boundaries() {
+        let visits = |start: &str, path: &str| {
+            let crawl_state = CrawlState::new(&Url::parse(&format!("https://example.com{}", start)).unwrap(), false);
+            crawl_state.should_visit_url(&Url::parse(&format!("https://example.com{}", path)).unwrap())
+        };
+
+        // Without a trailing slash, the start path is matched as a whole segment
+        assert!(visits("/docs", "/docs"));
+        assert!(visits("/docs", "/docs/"));
+        assert!(visits("/docs", "/docs/guide/intro.html"));
+        assert!(!visits("/docs", "/docs-archive/"));
+        assert!(!visits("/docs", "/docsv2"));
+        assert!(!visits("/docs", "/"));
+
+        // With a trailing slash, the directory is matched with or without its slash
+        assert!(visits("/docs/", "/docs/"));
+        assert!(visits("/docs/", "/docs"));
+        assert!(visits("/docs/", "/docs/guide/"));
+        assert!(!visits("/docs/", "/docs-archive/"));
+        assert!(!visits("/docs/", "/doc"));
+
+        // A file matches exactly, and anything below it if the server treats it as a directory
+        assert!(visits("/docs/index.html", "/docs/index.html"));
+        assert!(!visits("/docs/index.html", "/docs/index.html.bak"));
+        assert!(!visits("/docs/index.html", "/docs/other.html"));
+
+        // The root path includes every page
+        assert!(visits("/", "/"));
+        assert!(visits("/", "/docs-archive/"));
+        assert!(visits("/", "/a/b/c"));
+    }
+
+    #[test]
+    fn test_path_prefixes() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let crawl_state = CrawlState::new(&start_url, false).with_path_prefixes(["/blog/", "changelog"]);
+        let visits = |path: &str| crawl_state.should_visit_url(&Url::parse(&format!("https://example.com{}", path)).unwrap());
+
+        // The start URL's path stays in scope along with the other prefixes
+        assert!(visits("/docs/guide/"));
+        assert!(visits("/blog/2024/release.html"));
+        assert!(visits("/blog"));
+        assert!(visits("/changelog/v2/"));
+        assert!(!visits("/blog-archive/"));
+        assert!(!visits("/about/"));
+        assert!(!visits("/"));
+        assert!(!crawl_state.should_visit_url(&Url::parse("https://other.com/blog/").unwrap()));
+    }
+
     #[test]
     fn test_crawl_state_domain_matching() {
         let start_url = Url::parse("https://example.com/products/widgets/").unwrap();
@@ -222,6 +670,193 @@ mod tests {
         assert_eq!(CrawlState::normalize_url(&url3), "https://example.com/page.html");
     }
 
+    #[test]
+    fn test_normalize_url_sorts_query_parameters() {
+        let test_cases = vec![
+            ("https://example.com/page?a=1&b=2", "https://example.com/page?a=1&b=2"),
+            ("https://example.com/page?b=2&a=1", "https://example.com/page?a=1&b=2"),
+            ("https://example.com/page?b=2&a=1#section", "https://example.com/page?a=1&b=2"),
+            ("https://example.com/page?tag=z&tag=a&id=5", "https://example.com/page?id=5&tag=a&tag=z"),
+            ("https://example.com/page?", "https://example.com/page"),
+        ];
+
+        for (input, expected) in test_cases {
+            let url = Url::parse(input).unwrap();
+            assert_eq!(CrawlState::normalize_url(&url), expected, "Normalization failed for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_normalize_url_host_case() {
+        let test_cases = vec![
+            ("https://Example.com/x", "https://example.com/x"),
+            ("HTTPS://EXAMPLE.COM/Docs/Page.html?Lang=EN", "https://example.com/Docs/Page.html?Lang=EN"),
+            ("http://WWW.Example.com:8080/", "http://www.example.com:8080/"),
+        ];
+        for (input, expected) in test_cases {
+            let url = Url::parse(input).unwrap();
+            assert_eq!(CrawlState::normalize_url(&url), expected, "Normalization failed for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_mark_visited_host_case() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false);
+
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/x").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://Example.com/x").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("HTTPS://EXAMPLE.COM/x").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://EXAMPLE.com/").unwrap()));
+        // Paths and queries are case-sensitive
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/X").unwrap()));
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/x?q=A").unwrap()));
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/x?q=a").unwrap()));
+    }
+
+    #[test]
+    fn test_mark_visited_reordered_query() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false);
+
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/search?a=1&b=2").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/search?b=2&a=1").unwrap()));
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/search?a=1&b=3").unwrap()));
+    }
+
+    #[test]
+    fn test_mark_visited_ignore_query() {
+        let start_url = Url::parse("https://example.com/?ref=home").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false).with_ignore_query(true);
+
+        assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/").unwrap()));
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/page?page=1").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/page?page=2").unwrap()));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/page").unwrap()));
+    }
+
+    #[test]
+    fn test_trailing_slash_normalize() {
+        let cases = [
+            ("https://example.com/", "https://example.com/", "https://example.com/"),
+            ("https://example.com/docs", "https://example.com/docs/", "https://example.com/docs"),
+            ("https://example.com/docs/", "https://example.com/docs/", "https://example.com/docs"),
+            ("https://example.com/docs/page.html", "https://example.com/docs/page.html", "https://example.com/docs/page.html"),
+            ("https://example.com/docs/guide/?q=1", "https://example.com/docs/guide/?q=1", "https://example.com/docs/guide?q=1"),
+        ];
+        for (input, added, removed) in cases {
+            let url = Url::parse(input).unwrap();
+            assert_eq!(TrailingSlash::Add.normalize(url.clone()).as_str(), added, "adding a slash to {}", input);
+            assert_eq!(TrailingSlash::Remove.normalize(url).as_str(), removed, "removing the slash from {}", input);
+        }
+        assert_eq!("add".parse::<TrailingSlash>(), Ok(TrailingSlash::Add));
+        assert!("always".parse::<TrailingSlash>().is_err());
+    }
+
+    #[test]
+    fn test_mark_visited_trailing_slash() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false);
+        assert!(crawl_state.mark_visited(&Url::parse("https://example.com/docs").unwrap()));
+
+        for mode in [TrailingSlash::Add, TrailingSlash::Remove] {
+            let mut crawl_state = CrawlState::new(&start_url, false).with_trailing_slash(Some(mode));
+            assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/docs").unwrap()));
+            assert!(crawl_state.mark_visited(&Url::parse("https://example.com/docs/guide").unwrap()));
+            assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/docs/guide/").unwrap()));
+            assert!(crawl_state.mark_visited(&Url::parse("https://example.com/docs/page.html").unwrap()));
+            assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/docs/page.html").unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_domain_matching_subdomains() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let exact = CrawlState::new(&start_url, false);
+        let subdomains = CrawlState::new(&start_url, false).with_include_subdomains(true);
+
+        let cases = [
+            ("https://example.com/page", true, true),
+            ("https://EXAMPLE.com/page", true, true),
+            ("https://www.example.com/page", false, true),
+            ("https://docs.example.com/page", false, true),
+            ("https://api.v2.Example.COM/page", false, true),
+            ("https://notexample.com/page", false, false),
+            ("https://example.com.evil.net/page", false, false),
+            ("https://127.0.0.1/page", false, false),
+        ];
+        for (url, in_exact, in_subdomains) in cases {
+            let url = Url::parse(url).unwrap();
+            assert_eq!(exact.should_visit_url(&url), in_exact, "exact match for {}", url);
+            assert_eq!(subdomains.should_visit_url(&url), in_subdomains, "subdomain match for {}", url);
+        }
+
+        // Starting from www. includes the bare domain and its other subdomains
+        let www = CrawlState::new(&Url::parse("https://www.example.com/").unwrap(), true).with_include_subdomains(true);
+        assert!(www.is_in_domain(&Url::parse("https://example.com/").unwrap()));
+        assert!(www.is_in_domain(&Url::parse("https://blog.example.com/").unwrap()));
+        assert!(!www.is_in_domain(&Url::parse("https://example.org/").unwrap()));
+    }
+
+    #[test]
+    fn test_scope_with_ip_hosts() {
+        let ipv4 = CrawlState::new(&Url::parse("http://192.168.1.10/docs/").unwrap(), false).with_include_subdomains(true);
+        assert!(ipv4.should_visit_url(&Url::parse("http://192.168.1.10/docs/guide/").unwrap()));
+        assert!(!ipv4.should_visit_url(&Url::parse("http://192.168.1.10/other/").unwrap()));
+        assert!(!ipv4.should_visit_url(&Url::parse("http://192.168.1.11/docs/").unwrap()));
+
+        let ipv6 = CrawlState::new(&Url::parse("http://[::1]:8080/").unwrap(), true);
+        assert!(ipv6.should_visit_url(&Url::parse("http://[::1]:8080/page").unwrap()));
+        assert!(ipv6.should_visit_url(&Url::parse("http://[0:0:0:0:0:0:0:1]:8080/page").unwrap()));
+        assert!(!ipv6.should_visit_url(&Url::parse("http://[::2]:8080/page").unwrap()));
+        assert!(!ipv6.should_visit_url(&Url::parse("http://localhost:8080/page").unwrap()));
+    }
+
+    #[test]
+    fn test_scope_with_port() {
+        let crawl_state = CrawlState::new(&Url::parse("http://localhost:8080/docs/").unwrap(), false);
+        assert!(crawl_state.should_visit_url(&Url::parse("http://localhost:8080/docs/page").unwrap()));
+        assert!(!crawl_state.should_visit_url(&Url::parse("http://localhost:8080/other").unwrap()));
+        assert!(!crawl_state.should_visit_url(&Url::parse("http://otherhost:8080/docs/page").unwrap()));
+        // Like the scheme, the port doesn't affect the scope
+        assert!(crawl_state.should_visit_url(&Url::parse("https://localhost/docs/page").unwrap()));
+
+        // URLs without a host are never in scope
+        assert!(!crawl_state.should_visit_url(&Url::parse("file:///docs/page").unwrap()));
+        let hostless = CrawlState::new(&Url::parse("file:///docs/").unwrap(), false);
+        assert!(!hostless.should_visit_url(&Url::parse("file:///docs/page").unwrap()));
+    }
+
+    #[test]
+    fn test_same_scheme() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let any_scheme = CrawlState::new(&start_url, false);
+        let same_scheme = CrawlState::new(&start_url, false).with_same_scheme(true);
+
+        let https = Url::parse("https://example.com/docs/page").unwrap();
+        let http = Url::parse("http://example.com/docs/page").unwrap();
+        assert!(any_scheme.should_visit_url(&https));
+        assert!(any_scheme.should_visit_url(&http));
+        assert!(same_scheme.should_visit_url(&https));
+        assert!(!same_scheme.should_visit_url(&http));
+        assert!(same_scheme.is_in_domain(&http));
+        assert!(!same_scheme.is_in_scheme(&http));
+    }
+
+    #[test]
+    fn test_record_canonical() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false);
+        let tracked = Url::parse("https://example.com/docs/?utm_source=news").unwrap();
+        let unvisited = Url::parse("https://example.com/docs/guide/").unwrap();
+
+        assert!(!crawl_state.record_canonical(&start_url, &start_url));
+        assert!(crawl_state.record_canonical(&tracked, &start_url));
+        assert!(!crawl_state.record_canonical(&Url::parse("https://example.com/docs/guide").unwrap(), &unvisited));
+        assert_eq!(crawl_state.canonical_urls().get(tracked.as_str()).map(String::as_str), Some(start_url.as_str()));
+        assert_eq!(crawl_state.canonical_urls().len(), 2);
+    }
+
     #[test]
     fn test_mark_visited() {
         let start_url = Url::parse("https://example.com/").unwrap();
@@ -240,6 +875,30 @@ mod tests {
         assert!(!crawl_state.mark_visited(&url_with_fragment));
     }
 
+    #[test]
+    fn test_mark_visited_bloom_filter() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let mut crawl_state = CrawlState::new(&start_url, false)
+            .with_ignore_query(true)
+            .with_bloom_filter(1_000, 0.001);
+        // The start URL moves from the set into the filter
+        assert!(crawl_state.visited_pages().is_empty());
+        assert_eq!(crawl_state.visited_count(), 1);
+        assert!(!crawl_state.mark_visited(&start_url));
+
+        let url = Url::parse("https://example.com/page.html?b=2").unwrap();
+        assert!(crawl_state.mark_visited(&url));
+        assert!(!crawl_state.mark_visited(&Url::parse("https://example.com/page.html?a=1#section").unwrap()));
+        assert_eq!(crawl_state.visited_count(), 2);
+
+        let canonical = Url::parse("https://example.com/other.html").unwrap();
+        assert!(crawl_state.record_canonical(&canonical, &url));
+
+        let mut read: CrawlState = serde_json::from_str(&serde_json::to_string(&crawl_state).unwrap()).unwrap();
+        assert_eq!(read.visited_count(), 2);
+        assert!(!read.mark_visited(&url));
+    }
+
     // ===== HTML Content Detection Tests =====
 
     #[test]
@@ -275,6 +934,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_html_detection_overrides() {
+        let url = |path: &str| Url::parse("https://example.com/").unwrap().join(path).unwrap();
+        let detection = HtmlDetection::default()
+            .with_extensions([".xhtml", "SHTML", " "])
+            .extensionless_html(false);
+
+        assert!(detection.is_likely_html(&url("/page.xhtml")));
+        assert!(detection.is_likely_html(&url("/page.shtml")));
+        assert!(detection.is_likely_html(&url("/page.html")));
+        assert!(detection.is_likely_html(&url("/docs/")));
+        assert!(!detection.is_likely_html(&url("/api/v1/users")));
+        assert!(!detection.is_likely_html(&url("/image.png")));
+
+        // The defaults match is_likely_html_content
+        assert!(!HtmlDetection::default().is_likely_html(&url("/page.xhtml")));
+        assert!(HtmlDetection::default().is_likely_html(&url("/api/v1/users")));
+    }
+
     #[test]
     fn test_is_absolute_url() {
         // Absolute URLs
@@ -305,6 +983,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_extract_css_urls() {
+        let css = r#"
+            body { background: url(images/bg.png) no-repeat; }
+            @font-face { src: url("fonts/font.woff2") format("woff2"), url('fonts/font.woff'); }
+            .icon { background-image: url( "icons/icon.svg" ); }
+            .inline { background: url(data:image/png;base64,iVBORw0KGgo=); }
+        "#;
+
+        assert_eq!(
+            extract_css_urls(css),
+            vec!["images/bg.png", "fonts/font.woff2", "fonts/font.woff", "icons/icon.svg"]
+        );
+        assert!(extract_css_urls("color: red;").is_empty());
+    }
+
+    #[test]
+    fn test_extract_srcset_urls() {
+        assert_eq!(
+            extract_srcset_urls("img-480.jpg 480w, img-960.jpg 960w"),
+            vec!["img-480.jpg", "img-960.jpg"]
+        );
+        assert_eq!(
+            extract_srcset_urls("\n  small.png 1x,\n\tlarge.png   2x ,\n  "),
+            vec!["small.png", "large.png"]
+        );
+        assert_eq!(extract_srcset_urls("a.png 1x,b.png 2x"), vec!["a.png", "b.png"]);
+        assert_eq!(extract_srcset_urls("a.png, b.png 2x"), vec!["a.png", "b.png"]);
+        assert_eq!(extract_srcset_urls("only.png"), vec!["only.png"]);
+        assert_eq!(
+            extract_srcset_urls("/img/a,b.png 1x, data:image/png;base64,iVBORw0KGgo= 2x, /img/c.png 3x"),
+            vec!["/img/a,b.png", "/img/c.png"]
+        );
+        assert!(extract_srcset_urls("  ").is_empty());
+    }
+
+    #[test]
+    fn test_validate_contact_link() {
+        let valid = [
+            "mailto:user@example.com",
+            "MAILTO:first.last+tag@mail.example.co.uk",
+            "mailto:a@example.com,b@example.org?subject=Hello%20there",
+            "mailto:user%40example.com",
+            "mailto:?subject=no-recipient",
+            "tel:+1-555-123-4567",
+            "tel:+44 (20) 7946 0958",
+            "tel:555.0100;ext=12",
+            "tel:%2B33123456789",
+        ];
+        for href in valid {
+            assert_eq!(validate_contact_link(href), Some(true), "{} should be valid", href);
+        }
+
+        let invalid = [
+            "mailto:user@example,com",
+            "mailto:user.example.com",
+            "mailto:user@@example.com",
+            "mailto:user@example",
+            "mailto:user@-example.com",
+            "tel:",
+            "tel:call-me",
+            "tel:+1-555-CALL-NOW",
+            "tel:12",
+            "tel:+1234567890123456",
+        ];
+        for href in invalid {
+            assert_eq!(validate_contact_link(href), Some(false), "{} should be invalid", href);
+        }
+
+        assert_eq!(validate_contact_link("https://example.com/"), None);
+        assert_eq!(validate_contact_link("page.html"), None);
+    }
+
     // ===== Format Bytes Tests =====
 
     #[test]
@@ -314,6 +1065,24 @@ mod tests {
         assert_eq!(format_bytes(1536), "1.5 KB");
         assert_eq!(format_bytes(1048576), "1.0 MB");
         assert_eq!(format_bytes(2621440), "2.5 MB");
+        assert_eq!(format_bytes(1073741824), "1.0 GB");
+        assert_eq!(format_bytes(1610612736), "1.5 GB");
+        assert_eq!(format_bytes(1099511627776), "1.0 TB");
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("https://example.com/page"), "https://example.com/page");
+        assert_eq!(escape_xml("https://example.com/?a=1&b=2"), "https://example.com/?a=1&amp;b=2");
+        assert_eq!(escape_xml(r#"<a href="x">'y'</a>"#), "&lt;a href=&quot;x&quot;&gt;&apos;y&apos;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn test_escape_csv() {
+        assert_eq!(escape_csv("https://example.com/page"), "https://example.com/page");
+        assert_eq!(escape_csv("text/html; charset=utf-8"), "text/html; charset=utf-8");
+        assert_eq!(escape_csv("https://example.com/?a=1,2"), "\"https://example.com/?a=1,2\"");
+        assert_eq!(escape_csv(r#"say "hi""#), r#""say ""hi""""#);
     }
 
     #[test]
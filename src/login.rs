@@ -0,0 +1,49 @@
+use thiserror::Error;
+use ureq::Agent;
+use url::Url;
+
+use crate::cookies::CookieJar;
+
+/// Why logging in through a login form failed
+#[derive(Debug, Error)]
+pub enum LoginError {
+    #[error("the login URL {0} is not on the start URL's host, so its session cookie would never be sent")]
+    OtherHost(String),
+    #[error("could not reach the login URL {0}: {1}")]
+    Request(String, String),
+    #[error("the login form at {0} responded with status {1}, check the login data")]
+    Rejected(String, u16),
+}
+
+/// Log in by submitting form data such as `user=x&pass=y` to a login URL with a POST request.
+/// The session cookies it sets are stored in the cookie jar, which then sends them with every
+/// request to the start URL's host. The response isn't followed if it redirects, since login
+/// forms usually set their cookie on the redirect itself. Any 4xx or 5xx status is a failure.
+pub fn login(client: &Agent, login_url: &Url, form_data: &str, cookies: &CookieJar) -> Result<(), LoginError> {
+    if !cookies.is_start_host(login_url) {
+        return Err(LoginError::OtherHost(login_url.to_string()));
+    }
+
+    let mut request = client.post(login_url.as_str()).header("Content-Type", "application/x-www-form-urlencoded");
+    if let Some(cookie) = cookies.header_for(login_url) {
+        request = request.header("Cookie", cookie);
+    }
+    let response = request
+        .config()
+        .max_redirects(0)
+        .http_status_as_error(false)
+        .build()
+        .send(form_data)
+        .map_err(|err| LoginError::Request(login_url.to_string(), err.to_string()))?;
+
+    let status = response.status().as_u16();
+    if status >= 400 {
+        return Err(LoginError::Rejected(login_url.to_string(), status));
+    }
+    for set_cookie in response.headers().get_all("set-cookie") {
+        if let Ok(set_cookie) = set_cookie.to_str() {
+            cookies.store(login_url, set_cookie);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use log::{info, warn};
+use regex::Regex;
+use url::Url;
+
+use crate::crawler::coalesce_bad_urls;
+use crate::{BadUrl, CheckedUrl, ErrorKind, LinkChecker, UrlResults};
+
+/// The destination of an inline link `[text](url "title")` or image `![alt](src)`.
+/// Matching on `](` also finds links whose text is itself an image, as in badges.
+static INLINE_LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\]\(\s*(<[^>\n]*>|[^\s()]*(?:\([^\s()]*\)[^\s()]*)*)(?:\s+(?:"[^"\n]*"|'[^'\n]*'))?\s*\)"#).unwrap()
+});
+
+/// The destination of a link reference definition `[label]: url "title"`
+static REFERENCE_DEFINITION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}\[[^\]]+\]:\s*(<[^>]*>|\S+)").unwrap());
+
+static CODE_SPAN_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`+[^`]*`+").unwrap());
+
+/// Extract the destinations of all inline links, images and link reference definitions
+/// in a Markdown document, skipping fenced code blocks and code spans
+pub fn extract_markdown_links(markdown: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            fence = Some("```");
+            continue;
+        }
+        if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+            continue;
+        }
+
+        let line = CODE_SPAN_REGEX.replace_all(line, "");
+        let destinations = REFERENCE_DEFINITION_REGEX
+            .captures_iter(&line)
+            .chain(INLINE_LINK_REGEX.captures_iter(&line))
+            .filter_map(|captures| captures.get(1));
+        for destination in destinations {
+            let destination = destination.as_str();
+            let destination = destination.strip_prefix('<').and_then(|d| d.strip_suffix('>')).unwrap_or(destination);
+            if !destination.is_empty() {
+                links.push(destination.to_string());
+            }
+        }
+    }
+    links
+}
+
+/// Every Markdown file under a directory, in sorted order, skipping hidden files and directories
+pub fn find_markdown_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            files.extend(find_markdown_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Where a link found in a Markdown file points to
+#[derive(Debug, PartialEq)]
+enum LinkTarget {
+    /// Checked over HTTP
+    Remote(Url),
+    /// Checked against the filesystem
+    File(PathBuf),
+}
+
+/// Resolve a link found in a Markdown file. Relative links point to files next to it; links
+/// starting with `/` point to the base URL if there is one, or else to the input directory.
+/// Other schemes such as `mailto:` and in-page `#fragment` links aren't checked.
+fn resolve_link(link: &str, file_url: &Url, root_url: &Url, base_url: Option<&Url>) -> Option<LinkTarget> {
+    if link.starts_with('#') {
+        return None;
+    }
+    if let Ok(url) = Url::parse(link) {
+        return matches!(url.scheme(), "http" | "https").then_some(LinkTarget::Remote(url));
+    }
+    if link.starts_with("//") {
+        return file_url.join(link).ok().map(LinkTarget::Remote);
+    }
+
+    let resolved = match (link.strip_prefix('/'), base_url) {
+        (Some(_), Some(base_url)) => return base_url.join(link).ok().map(LinkTarget::Remote),
+        (Some(root_relative), None) => root_url.join(root_relative).ok()?,
+        (None, _) => file_url.join(link).ok()?,
+    };
+    // The fragment and query don't matter for a file on disk
+    resolved.to_file_path().ok().map(LinkTarget::File)
+}
+
+/// Check the links in every Markdown file under a directory. Links to websites are checked with
+/// the link checker; links to other files are checked against the filesystem. In the results,
+/// each file's links are listed under its `file://` URL, and links to files by their `file://` URL.
+pub fn check_markdown_dir(checker: &LinkChecker, dir: &Path, base_url: Option<&Url>) -> io::Result<UrlResults> {
+    let dir = dir.canonicalize()?;
+    let root_url = Url::from_directory_path(&dir).map_err(|()| io::Error::other(format!("invalid directory: {}", dir.display())))?;
+
+    let mut results = UrlResults::default();
+    let mut remote_links = Vec::new();
+    let files = find_markdown_files(&dir)?;
+    info!("Checking links in {} Markdown files under {}", files.len(), dir.display());
+    for file in files {
+        let Ok(file_url) = Url::from_file_path(&file) else {
+            continue;
+        };
+        let markdown = fs::read_to_string(&file)?;
+        let mut found_links = Vec::new();
+        for link in extract_markdown_links(&markdown) {
+            match resolve_link(&link, &file_url, &root_url, base_url) {
+                Some(LinkTarget::Remote(url)) => {
+                    found_links.push(url.to_string());
+                    remote_links.push((url, Some(file_url.clone())));
+                }
+                Some(LinkTarget::File(path)) => {
+                    // Like web links, file links are reported resolved, so links to the same file match
+                    let file_link = Url::from_file_path(&path).map_or(link, |url| url.to_string());
+                    found_links.push(file_link.clone());
+                    let exists = path.exists();
+                    results.checked_urls.push(CheckedUrl {
+                        url: file_link.clone(),
+                        source_page: Some(file_url.to_string()),
+                        status: None,
+                        content_type: None,
+                        bytes: None,
+                        duration_ms: None,
+                        content_hash: None,
+                        ok: exists,
+                    });
+                    if !exists {
+                        warn!("Missing file {} linked from {}", path.display(), file.display());
+                        results.bad_urls.push(BadUrl {
+                            url: file_link,
+                            found_on: vec![file_url.to_string()],
+                            status: None,
+                            error_kind: ErrorKind::MissingFile,
+                            reason: format!("file not found: {}", path.display()),
+                            link_text: None,
+                        });
+                    }
+                }
+                None => {}
+            }
+        }
+        results.url_map.insert(file_url.to_string(), found_links);
+    }
+
+    let remote_results = checker.check_urls(remote_links);
+    results.bad_urls.extend(remote_results.bad_urls);
+    results.bad_urls = coalesce_bad_urls(results.bad_urls, &results.url_map);
+    results.checked_urls.extend(remote_results.checked_urls);
+    results.redirects.extend(remote_results.redirects);
+    results.headers.extend(remote_results.headers);
+    results.bytes_downloaded = remote_results.bytes_downloaded;
+    results.estimated_bytes = remote_results.estimated_bytes;
+    results.requests = remote_results.requests;
+    results.thread_metrics = remote_results.thread_metrics;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_markdown_links() {
+        let markdown = r#"# Guide
+
+See [the install docs](install.md#setup) and [the site](https://example.com/ "Example").
+![Logo](<images/my logo.png>) [![Badge](https://img.example.com/badge.svg)](https://ci.example.com/)
+[Wiki](https://en.wikipedia.org/wiki/Rust_(programming_language))
+
+Inline `[not](a-link.md)` code.
+
+```markdown
+[also not](a-link.md)
+```
+
+[ref]: ../reference.md "Reference"
+"#;
+        assert_eq!(
+            extract_markdown_links(markdown),
+            vec![
+                "install.md#setup",
+                "https://example.com/",
+                "images/my logo.png",
+                "https://img.example.com/badge.svg",
+                "https://ci.example.com/",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "../reference.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let root_url = Url::parse("file:///docs/").unwrap();
+        let file_url = Url::parse("file:///docs/guide/intro.md").unwrap();
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let file = |path: &str| Some(LinkTarget::File(PathBuf::from(path)));
+
+        assert_eq!(resolve_link("setup.md#install", &file_url, &root_url, None), file("/docs/guide/setup.md"));
+        assert_eq!(resolve_link("../my%20file.md?raw", &file_url, &root_url, None), file("/docs/my file.md"));
+        assert_eq!(resolve_link("/api/index.md", &file_url, &root_url, None), file("/docs/api/index.md"));
+        assert_eq!(
+            resolve_link("/api/", &file_url, &root_url, Some(&base_url)),
+            Some(LinkTarget::Remote(Url::parse("https://example.com/api/").unwrap()))
+        );
+        assert_eq!(
+            resolve_link("https://example.com/x", &file_url, &root_url, None),
+            Some(LinkTarget::Remote(Url::parse("https://example.com/x").unwrap()))
+        );
+        assert_eq!(resolve_link("#section", &file_url, &root_url, None), None);
+        assert_eq!(resolve_link("mailto:me@example.com", &file_url, &root_url, None), None);
+    }
+}
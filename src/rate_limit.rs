@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+/// A token bucket for a single host
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Limits the request rate to each host with a token bucket per host.
+/// Each bucket holds up to one second's worth of requests, so short bursts are
+/// allowed while the sustained rate stays at `requests_per_second`.
+pub struct RateLimiter {
+    requests_per_second: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> RateLimiter {
+        assert!(requests_per_second > 0.0, "rate limit must be positive");
+        RateLimiter {
+            requests_per_second,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Block until a request to the URL's host is allowed
+    pub fn acquire(&self, url: &Url) {
+        let wait = self.reserve(url.host_str().unwrap_or_default());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Take a token from the host's bucket and return how long to wait before using it.
+    /// The bucket may go negative so that waiting threads queue up behind each other.
+    fn reserve(&self, host: &str) -> Duration {
+        let capacity = self.requests_per_second.max(1.0);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(capacity);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            // A rate so low that the wait doesn't fit in a Duration waits as long as possible
+            Duration::try_from_secs_f64(-bucket.tokens / self.requests_per_second).unwrap_or(Duration::MAX)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_up_to_capacity() {
+        let limiter = RateLimiter::new(5.0);
+
+        for _ in 0..5 {
+            assert_eq!(limiter.reserve("example.com"), Duration::ZERO);
+        }
+        // The sixth request has to wait for a token to be refilled
+        let wait = limiter.reserve("example.com");
+        assert!(wait > Duration::from_millis(150) && wait <= Duration::from_millis(200), "unexpected wait {:?}", wait);
+    }
+
+    #[test]
+    fn test_waiting_requests_queue_up() {
+        let limiter = RateLimiter::new(1.0);
+
+        assert_eq!(limiter.reserve("example.com"), Duration::ZERO);
+        let second = limiter.reserve("example.com");
+        let third = limiter.reserve("example.com");
+        assert!(second > Duration::from_millis(900));
+        assert!(third > Duration::from_millis(1900));
+    }
+
+    #[test]
+    fn test_hosts_are_limited_independently() {
+        let limiter = RateLimiter::new(1.0);
+
+        assert_eq!(limiter.reserve("example.com"), Duration::ZERO);
+        assert!(limiter.reserve("example.com") > Duration::ZERO);
+        assert_eq!(limiter.reserve("docs.example.com"), Duration::ZERO);
+        assert_eq!(limiter.reserve("other.com"), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_tiny_rate() {
+        let limiter = RateLimiter::new(1e-300);
+
+        assert_eq!(limiter.reserve("example.com"), Duration::ZERO);
+        assert_eq!(limiter.reserve("example.com"), Duration::MAX);
+    }
+}
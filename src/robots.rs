@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use log::warn;
+use ureq::Agent;
+use url::Url;
+
+/// Rules from a robots.txt file that apply to this crawler.
+/// Paths are matched by prefix; the longest matching rule wins, with `Allow`
+/// taking precedence over `Disallow` when both match equally.
+#[derive(Debug, Default)]
+pub struct Robots {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Fetch and parse `/robots.txt` for the host of the given URL, using the rules for `user_agent`.
+    /// A missing or unreadable robots.txt allows everything.
+    pub fn fetch(client: &Agent, start_url: &Url, user_agent: &str) -> Robots {
+        let Ok(robots_url) = start_url.join("/robots.txt") else {
+            return Robots::default();
+        };
+
+        match client.get(robots_url.as_str()).call() {
+            Ok(mut response) => match response.body_mut().read_to_string() {
+                Ok(content) => Robots::parse(&content, user_agent),
+                Err(err) => {
+                    warn!("Could not read {robots_url:#}: {err}");
+                    Robots::default()
+                }
+            },
+            Err(ureq::Error::StatusCode(_)) => Robots::default(),
+            Err(err) => {
+                warn!("Could not fetch {robots_url:#}: {err}");
+                Robots::default()
+            }
+        }
+    }
+
+    /// Parse the rules of a robots.txt file that apply to the given user agent.
+    /// Rules in a group whose name appears in the user agent (e.g. `link-checker` in
+    /// `link-checker/0.5.0`) are used; otherwise the `*` group applies.
+    pub fn parse(content: &str, user_agent: &str) -> Robots {
+        let user_agent = user_agent.to_lowercase();
+        let mut specific = Robots::default();
+        let mut wildcard = Robots::default();
+        let mut found_specific = false;
+
+        // Agents named by the current group, and whether we are still reading its User-agent lines
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_agent_lines = false;
+
+        for line in content.lines() {
+            // Strip comments and surrounding whitespace
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = field.trim().to_lowercase();
+            let value = value.trim();
+
+            if field == "user-agent" {
+                if !in_agent_lines {
+                    group_agents.clear();
+                    in_agent_lines = true;
+                }
+                group_agents.push(value.to_lowercase());
+                continue;
+            }
+            in_agent_lines = false;
+
+            let target = if group_agents.iter().any(|agent| agent != "*" && user_agent.contains(agent.as_str())) {
+                found_specific = true;
+                &mut specific
+            } else if group_agents.iter().any(|agent| agent == "*") {
+                &mut wildcard
+            } else {
+                continue;
+            };
+
+            match field.as_str() {
+                // An empty Disallow means everything is allowed
+                "disallow" if !value.is_empty() => target.disallow.push(value.to_string()),
+                "allow" if !value.is_empty() => target.allow.push(value.to_string()),
+                // Seconds to wait between requests, which may be fractional
+                "crawl-delay" => {
+                    // A delay too long for a Duration is ignored like any other invalid value
+                    if let Some(delay) = value.parse::<f64>().ok().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()) {
+                        target.crawl_delay = Some(delay);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if found_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    /// How long the site asks crawlers to wait between requests with a `Crawl-delay` line, if at all
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Determine whether the given URL may be crawled according to these rules
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        let longest_match = |rules: &[String]| {
+            rules
+                .iter()
+                .filter(|rule| path.starts_with(rule.as_str()))
+                .map(|rule| rule.len())
+                .max()
+        };
+
+        match (longest_match(&self.allow), longest_match(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(allow), Some(disallow)) => allow >= disallow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_USER_AGENT;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/").unwrap().join(path).unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_group_disallow() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /private/\nDisallow: /tmp", DEFAULT_USER_AGENT);
+
+        assert!(robots.is_allowed(&url("/")));
+        assert!(robots.is_allowed(&url("/public/page.html")));
+        assert!(!robots.is_allowed(&url("/private/")));
+        assert!(!robots.is_allowed(&url("/private/page.html")));
+        assert!(!robots.is_allowed(&url("/tmp/file")));
+        assert!(!robots.is_allowed(&url("/tmpfile")));
+    }
+
+    #[test]
+    fn test_specific_group_overrides_wildcard() {
+        let content = "\
+User-agent: *
+Disallow: /
+
+User-agent: link-checker
+Disallow: /admin/
+";
+        let robots = Robots::parse(content, DEFAULT_USER_AGENT);
+
+        assert!(robots.is_allowed(&url("/docs/")));
+        assert!(!robots.is_allowed(&url("/admin/settings")));
+    }
+
+    #[test]
+    fn test_other_agents_ignored() {
+        let content = "\
+User-agent: googlebot
+Disallow: /
+
+User-agent: *
+Disallow: /search
+";
+        let robots = Robots::parse(content, DEFAULT_USER_AGENT);
+
+        assert!(robots.is_allowed(&url("/docs/")));
+        assert!(!robots.is_allowed(&url("/search?q=test")));
+    }
+
+    #[test]
+    fn test_shared_group_and_comments() {
+        let content = "\
+# Comment line
+User-agent: googlebot
+User-agent: Link-Checker # shared group
+Disallow: /drafts/ # unfinished pages
+";
+        let robots = Robots::parse(content, DEFAULT_USER_AGENT);
+
+        assert!(!robots.is_allowed(&url("/drafts/post")));
+        assert!(robots.is_allowed(&url("/posts/")));
+    }
+
+    #[test]
+    fn test_allow_overrides_shorter_disallow() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /docs/\nAllow: /docs/public/", DEFAULT_USER_AGENT);
+
+        assert!(!robots.is_allowed(&url("/docs/internal")));
+        assert!(robots.is_allowed(&url("/docs/public/guide")));
+    }
+
+    #[test]
+    fn test_crawl_delay() {
+        let content = "\
+User-agent: *
+Crawl-delay: 10
+
+User-agent: link-checker
+Disallow: /admin/
+Crawl-delay: 0.5
+";
+        assert_eq!(Robots::parse(content, DEFAULT_USER_AGENT).crawl_delay(), Some(Duration::from_millis(500)));
+        assert_eq!(Robots::parse(content, "other-bot/1.0").crawl_delay(), Some(Duration::from_secs(10)));
+
+        assert_eq!(Robots::parse("User-agent: *\nDisallow: /tmp/", DEFAULT_USER_AGENT).crawl_delay(), None);
+        assert_eq!(Robots::parse("User-agent: *\nCrawl-delay: soon", DEFAULT_USER_AGENT).crawl_delay(), None);
+        assert_eq!(Robots::parse("User-agent: *\nCrawl-delay: -1", DEFAULT_USER_AGENT).crawl_delay(), None);
+        assert_eq!(Robots::parse("User-agent: *\nCrawl-delay: 1e20", DEFAULT_USER_AGENT).crawl_delay(), None);
+        assert_eq!(Robots::parse("User-agent: *\nCrawl-delay: inf", DEFAULT_USER_AGENT).crawl_delay(), None);
+    }
+
+    #[test]
+    fn test_empty_disallow_allows_everything() {
+        let robots = Robots::parse("User-agent: *\nDisallow:", DEFAULT_USER_AGENT);
+        assert!(robots.is_allowed(&url("/anything")));
+
+        let robots = Robots::parse("", DEFAULT_USER_AGENT);
+        assert!(robots.is_allowed(&url("/anything")));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use serde::Serialize;
+
+use crate::{BadUrl, ErrorKind, UrlResults};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF 2.1.0 log, as consumed by code-scanning tools such as GitHub's security tab
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: &'static str,
+    pub runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Run {
+    pub tool: Tool,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Driver {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub information_uri: &'static str,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: &'static str,
+    pub short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Message {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: &'static str,
+    pub level: &'static str,
+    pub message: Message,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhysicalLocation {
+    pub artifact_location: ArtifactLocation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtifactLocation {
+    pub uri: String,
+}
+
+/// The rule a broken link violates
+fn rule_id(bad_url: &BadUrl) -> &'static str {
+    match bad_url.error_kind {
+        ErrorKind::MissingAnchor => "missing-anchor",
+        _ => "broken-link",
+    }
+}
+
+/// Every page a broken link was found on. A broken start URL has no page linking to it,
+/// so it is its own location.
+fn locations(bad_url: &BadUrl) -> Vec<Location> {
+    let pages = if bad_url.found_on.is_empty() {
+        std::slice::from_ref(&bad_url.url)
+    } else {
+        bad_url.found_on.as_slice()
+    };
+    pages
+        .iter()
+        .map(|page| Location {
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation { uri: page.clone() },
+            },
+        })
+        .collect()
+}
+
+/// Build a SARIF log where each broken link is a result located on the page it was found on
+pub fn sarif_report(url_results: &UrlResults) -> SarifLog {
+    let rules = vec![
+        Rule {
+            id: "broken-link",
+            short_description: Message { text: "Link to a URL that could not be fetched".to_string() },
+        },
+        Rule {
+            id: "missing-anchor",
+            short_description: Message { text: "Link to an anchor that does not exist on the target page".to_string() },
+        },
+    ];
+
+    let results = url_results
+        .bad_urls
+        .iter()
+        .map(|bad_url| SarifResult {
+            rule_id: rule_id(bad_url),
+            level: "error",
+            message: Message { text: format!("{} ({})", bad_url.url, bad_url.reason) },
+            locations: locations(bad_url),
+        })
+        .collect();
+
+    SarifLog {
+        schema: SARIF_SCHEMA,
+        version: "2.1.0",
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "link-checker",
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: "https://github.com/A2-ai/link-checker",
+                    rules,
+                },
+            },
+            results,
+        }],
+    }
+}
+
+/// Write a SARIF 2.1.0 report of the broken links to this path
+pub fn write_sarif_report(url_results: &UrlResults, path: &str) -> std::io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &sarif_report(url_results))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sarif_report() {
+        let url_results = UrlResults {
+            bad_urls: vec![
+                BadUrl {
+                    url: "https://example.com/missing.html".to_string(),
+                    found_on: vec!["https://example.com/".to_string(), "https://example.com/about/".to_string()],
+                    status: Some(404),
+                    error_kind: ErrorKind::HttpStatus,
+                    reason: "bad http response: 404".to_string(),
+                    link_text: None,
+                },
+                BadUrl {
+                    url: "https://example.com/#nowhere".to_string(),
+                    found_on: Vec::new(),
+                    status: None,
+                    error_kind: ErrorKind::MissingAnchor,
+                    reason: "missing anchor #nowhere".to_string(),
+                    link_text: None,
+                },
+            ],
+            ..UrlResults::default()
+        };
+
+        let sarif = serde_json::to_value(sarif_report(&url_results)).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["$schema"], SARIF_SCHEMA);
+
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "link-checker");
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "broken-link");
+        assert_eq!(results[0]["message"]["text"], "https://example.com/missing.html (bad http response: 404)");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "https://example.com/"
+        );
+        assert_eq!(
+            results[0]["locations"][1]["physicalLocation"]["artifactLocation"]["uri"],
+            "https://example.com/about/"
+        );
+        assert_eq!(results[1]["ruleId"], "missing-anchor");
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "https://example.com/#nowhere"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use regex::Regex;
+use ureq::Agent;
+use url::Url;
+
+static LOC_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</loc>").unwrap());
+static LASTMOD_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<lastmod>\s*(.*?)\s*</lastmod>").unwrap());
+/// A `<url>` entry of a `<urlset>`, or a `<sitemap>` entry of a `<sitemapindex>`
+static ENTRY_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<(?:url|sitemap)\b[^>]*>(.*?)</(?:url|sitemap)>").unwrap());
+
+/// A page of a `<urlset>`, or a further sitemap of a `<sitemapindex>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub url: Url,
+    /// When the page or sitemap last changed, if the sitemap says so and the date is valid
+    pub lastmod: Option<SystemTime>,
+}
+
+/// How many levels of nested sitemap index files are followed
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+/// Parse the `<loc>` entries of a sitemap. For a `<urlset>` these are the site's pages;
+/// for a `<sitemapindex>` they are the URLs of further sitemaps.
+/// Entries that aren't valid absolute URLs are ignored.
+pub fn parse_sitemap(xml: &str) -> Vec<Url> {
+    parse_sitemap_entries(xml).into_iter().map(|entry| entry.url).collect()
+}
+
+/// Parse the entries of a sitemap with their `<lastmod>` dates, like `parse_sitemap`
+pub fn parse_sitemap_entries(xml: &str) -> Vec<SitemapEntry> {
+    ENTRY_REGEX
+        .captures_iter(xml)
+        .filter_map(|entry| {
+            let loc = LOC_REGEX.captures(&entry[1])?;
+            let url = Url::parse(&unescape_xml(loc[1].trim())).ok()?;
+            let lastmod = LASTMOD_REGEX.captures(&entry[1]).and_then(|lastmod| parse_lastmod(&lastmod[1]));
+            Some(SitemapEntry { url, lastmod })
+        })
+        .collect()
+}
+
+/// The days from 1970-01-01 to a date, counting years from March so the leap day comes last
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parse a sitemap date in the W3C format: a date like `2024-05-01`, or a timestamp like
+/// `2024-05-01T12:30:00+02:00`, with or without seconds, their fractions and the offset from UTC
+pub fn parse_lastmod(text: &str) -> Option<SystemTime> {
+    let text = text.trim();
+    let (date, time) = match text.split_once(['T', 't']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut date_parts = date.split('-');
+    let year: i64 = date_parts.next().filter(|year| year.len() == 4)?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok().filter(|month| (1..=12).contains(month))?;
+    let day: i64 = date_parts.next()?.parse().ok().filter(|day| (1..=31).contains(day))?;
+    if date_parts.next().is_some() {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+
+    if let Some(time) = time {
+        let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+            (clock, 0)
+        } else if let Some(sign_index) = time.rfind(['+', '-']) {
+            let (hours, minutes) = time[sign_index + 1..].split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3_600 + minutes.parse::<i64>().ok()? * 60;
+            (&time[..sign_index], if time[sign_index..].starts_with('-') { -offset } else { offset })
+        } else {
+            (time, 0)
+        };
+        let mut clock_parts = clock.split(':');
+        let hours: i64 = clock_parts.next()?.parse().ok().filter(|hours| (0..24).contains(hours))?;
+        let minutes: i64 = clock_parts.next()?.parse().ok().filter(|minutes| (0..60).contains(minutes))?;
+        let clock_seconds = match clock_parts.next() {
+            Some(clock_seconds) => clock_seconds.parse::<f64>().ok().filter(|clock_seconds| (0.0..61.0).contains(clock_seconds))? as i64,
+            None => 0,
+        };
+        if clock_parts.next().is_some() {
+            return None;
+        }
+        seconds += hours * 3_600 + minutes * 60 + clock_seconds - offset;
+    }
+
+    if seconds >= 0 {
+        Some(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+    } else {
+        Some(UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+/// Whether the sitemap is an index of other sitemaps rather than a list of pages
+pub fn is_sitemap_index(xml: &str) -> bool {
+    xml.contains("<sitemapindex")
+}
+
+/// Fetch `/sitemap.xml` for the host of the given URL and return every page it lists,
+/// following sitemap index files. A missing or unreadable sitemap yields no pages.
+pub fn fetch_sitemap(client: &Agent, start_url: &Url) -> Vec<Url> {
+    fetch_sitemap_modified_since(client, start_url, None)
+}
+
+/// Like `fetch_sitemap`, but only the pages whose `<lastmod>` is on or after `since`, if given.
+/// Sitemaps in an index whose `<lastmod>` is older aren't fetched, as none of their pages
+/// changed since. Entries without a valid `<lastmod>` are kept, as they may have changed.
+pub fn fetch_sitemap_modified_since(client: &Agent, start_url: &Url, since: Option<SystemTime>) -> Vec<Url> {
+    let fetch = |sitemap_url: &Url| -> Result<String, String> {
+        let mut response = client.get(sitemap_url.as_str()).call().map_err(|err| err.to_string())?;
+        response.body_mut().read_to_string().map_err(|err| err.to_string())
+    };
+    fetch_sitemap_pages(start_url, since, fetch).into_iter().map(|(page, _)| page).collect()
+}
+
+/// Like `fetch_sitemap_modified_since`, but each page comes with the URL of the sitemap that
+/// lists it, and each sitemap is fetched by calling `fetch`, which returns its body or why not
+pub fn fetch_sitemap_pages(
+    start_url: &Url,
+    since: Option<SystemTime>,
+    mut fetch: impl FnMut(&Url) -> Result<String, String>,
+) -> Vec<(Url, Url)> {
+    let Ok(sitemap_url) = start_url.join("/sitemap.xml") else {
+        return Vec::new();
+    };
+
+    let mut pages = Vec::new();
+    let mut fetched = HashSet::new();
+    let mut queue = vec![(sitemap_url, 0)];
+    while let Some((sitemap_url, depth)) = queue.pop() {
+        if !fetched.insert(sitemap_url.clone()) {
+            continue;
+        }
+
+        let xml = match fetch(&sitemap_url) {
+            Ok(xml) => xml,
+            Err(err) => {
+                warn!("Could not fetch {sitemap_url:#}: {err}");
+                continue;
+            }
+        };
+
+        let urls = parse_sitemap_entries(&xml)
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.lastmod.is_none_or(|lastmod| lastmod >= since)))
+            .map(|entry| entry.url);
+        if !is_sitemap_index(&xml) {
+            pages.extend(urls.map(|url| (url, sitemap_url.clone())));
+        } else if depth < MAX_SITEMAP_DEPTH {
+            queue.extend(urls.map(|url| (url, depth + 1)));
+        } else {
+            warn!("Ignoring sitemap index {sitemap_url:#}: nested too deeply");
+        }
+    }
+    pages
+}
+
+/// Decode the predefined XML entities
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_urlset() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/</loc>
+    <lastmod>2024-01-01</lastmod>
+  </url>
+  <url>
+    <loc>
+      https://example.com/search?q=a&amp;page=2
+    </loc>
+  </url>
+  <url><loc><![CDATA[https://example.com/docs/]]></loc></url>
+  <url><loc>not a url</loc></url>
+</urlset>"#;
+
+        let urls: Vec<String> = parse_sitemap(xml).into_iter().map(String::from).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/",
+                "https://example.com/search?q=a&page=2",
+                "https://example.com/docs/",
+            ]
+        );
+        assert!(!is_sitemap_index(xml));
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-pages.xml</loc></sitemap>
+  <sitemap><loc>https://example.com/sitemap-posts.xml</loc></sitemap>
+</sitemapindex>"#;
+
+        let urls: Vec<String> = parse_sitemap(xml).into_iter().map(String::from).collect();
+        assert_eq!(
+            urls,
+            vec!["https://example.com/sitemap-pages.xml", "https://example.com/sitemap-posts.xml"]
+        );
+        assert!(is_sitemap_index(xml));
+    }
+
+    #[test]
+    fn test_parse_sitemap_entries() {
+        let xml = r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc><lastmod>2024-05-01</lastmod></url>
+  <url>
+    <loc>https://example.com/news/</loc>
+    <changefreq>daily</changefreq>
+    <lastmod> 2024-05-01T12:30:00+02:00 </lastmod>
+  </url>
+  <url><loc>https://example.com/about/</loc></url>
+  <url><loc>https://example.com/old/</loc><lastmod>last week</lastmod></url>
+</urlset>"#;
+
+        let entries = parse_sitemap_entries(xml);
+        let lastmods: Vec<(&str, Option<SystemTime>)> = entries.iter().map(|entry| (entry.url.as_str(), entry.lastmod)).collect();
+        assert_eq!(
+            lastmods,
+            vec![
+                ("https://example.com/", parse_lastmod("2024-05-01")),
+                ("https://example.com/news/", parse_lastmod("2024-05-01T10:30:00Z")),
+                ("https://example.com/about/", None),
+                ("https://example.com/old/", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_lastmod() {
+        let at = |seconds: u64| Some(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(parse_lastmod("1970-01-01"), at(0));
+        assert_eq!(parse_lastmod("2000-02-29"), at(951_782_400));
+        assert_eq!(parse_lastmod("2024-05-01"), at(1_714_521_600));
+        assert_eq!(parse_lastmod("2024-05-01T12:30:00Z"), at(1_714_566_600));
+        assert_eq!(parse_lastmod("2024-05-01T12:30Z"), at(1_714_566_600));
+        assert_eq!(parse_lastmod("2024-05-01T12:30:00.75Z"), at(1_714_566_600));
+        assert_eq!(parse_lastmod("2024-05-01T14:30:00+02:00"), at(1_714_566_600));
+        assert_eq!(parse_lastmod("2024-05-01T07:30:00-05:00"), at(1_714_566_600));
+        // Without an offset, the time is taken as UTC
+        assert_eq!(parse_lastmod("2024-05-01T12:30:00"), at(1_714_566_600));
+        assert_eq!(parse_lastmod("1969-12-31"), Some(UNIX_EPOCH - Duration::from_secs(86_400)));
+
+        for invalid in ["", "yesterday", "2024-13-01", "2024-05-32", "2024-05", "24-05-01", "2024-05-01T25:00Z", "2024-05-01T12"] {
+            assert_eq!(parse_lastmod(invalid), None, "{:?} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_empty_sitemap() {
+        assert!(parse_sitemap("").is_empty());
+        assert!(parse_sitemap("<html><body>Not found</body></html>").is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CrawlState, UrlResults};
+
+/// A URL that was queued but not yet checked when the crawl stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUrl {
+    pub url: String,
+    pub source_page: Option<String>,
+    pub depth: usize,
+    /// Assets are only checked for availability, never crawled
+    pub asset: bool,
+    pub extract_links: bool,
+    /// The text of the link on the source page
+    #[serde(default)]
+    pub link_text: Option<String>,
+}
+
+/// Everything needed to pick up an unfinished crawl where it left off:
+/// the visited set, the URLs still waiting to be checked, and the results so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedCrawl {
+    pub state: CrawlState,
+    pub pending: Vec<PendingUrl>,
+    pub results: UrlResults,
+}
+
+impl SavedCrawl {
+    pub fn load(path: &Path) -> std::io::Result<SavedCrawl> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        // Dropping the writer would ignore an error writing the rest of the file
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let start_url = Url::parse("https://example.com/docs/").unwrap();
+        let mut state = CrawlState::new(&start_url, false);
+        state.mark_visited(&start_url.join("guide/").unwrap());
+        let saved = SavedCrawl {
+            state,
+            pending: vec![PendingUrl {
+                url: "https://example.com/docs/guide/".to_string(),
+                source_page: Some("https://example.com/docs/".to_string()),
+                depth: 1,
+                asset: false,
+                extract_links: true,
+                link_text: Some("Guide".to_string()),
+            }],
+            results: UrlResults::default(),
+        };
+
+        let path = std::env::temp_dir().join(format!("link-checker-state-{}.json", std::process::id()));
+        saved.save(&path).unwrap();
+        let loaded = SavedCrawl::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.state.visited_pages().len(), 2);
+        assert!(!loaded.state.clone().mark_visited(&start_url.join("guide/").unwrap()));
+        assert!(loaded.state.should_visit_url(&start_url.join("other").unwrap()));
+        assert_eq!(loaded.pending.len(), 1);
+        assert_eq!(loaded.pending[0].url, "https://example.com/docs/guide/");
+    }
+}
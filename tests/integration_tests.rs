@@ -1,8 +1,15 @@
 use url::Url;
 use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // Import the main crate functions
-use link_checker::{CrawlState, is_likely_html_content, format_bytes};
+use link_checker::{ContentTypeMismatch, CrawlState, CrawlStats, ErrorKind, LinkChecker, MetaRefresh, RequestCounts, UrlResults, is_likely_html_content, format_bytes};
+use link_checker::cookies::CookieJar;
+use link_checker::login::LoginError;
+use link_checker::markdown::check_markdown_dir;
+use link_checker::state::SavedCrawl;
 
 // Note: These integration tests would ideally use a mock HTTP server
 // For now, they test the core logic integration without network calls
@@ -111,7 +118,7 @@ fn test_url_normalization_and_deduplication() {
     let mut crawl_state = CrawlState::new(&start_url, true);
     
     // Test that URLs with different fragments are treated as the same
-    let urls_with_fragments = vec![
+    let urls_with_fragments = [
         "https://example.com/page.html",
         "https://example.com/page.html#section1",
         "https://example.com/page.html#section2",
@@ -192,8 +199,12 @@ fn test_bytes_formatting_edge_cases() {
         (1536, "1.5 KB"),
         (1048575, "1024.0 KB"),
         (1048576, "1.0 MB"),
-        (1073741824, "1024.0 MB"),
-        (2147483648, "2048.0 MB"),
+        (1073741823, "1024.0 MB"),
+        (1073741824, "1.0 GB"),
+        (1610612736, "1.5 GB"),
+        (2147483648, "2.0 GB"),
+        (1099511627775, "1024.0 GB"),
+        (1099511627776, "1.0 TB"),
     ];
     
     for (bytes, expected) in test_cases {
@@ -259,4 +270,1708 @@ fn test_matching_and_content_detection_interaction() {
             is_likely_html_content(&url)
         );
     }
-}
\ No newline at end of file
+}
+// ===== End-to-end crawl tests against a local server =====
+
+/// Serve the given routes (path, status code, content type and body) on a local port
+/// and return the base URL. Unknown paths get a 404, and `{port}` in a body is replaced with the port.
+/// For redirect status codes, the body is sent as the `Location` header instead.
+/// A `*` route answers every path that doesn't match another route, and paths ending in "slow" take 200ms to answer.
+fn serve<B: Into<String>>(routes: Vec<(&'static str, u16, &'static str, B)>) -> Url {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let routes: Vec<(&str, u16, &str, String)> = routes
+        .into_iter()
+        .map(|(path, status, content_type, body)| {
+            (path, status, content_type, body.into().replace("{port}", &port.to_string()))
+        })
+        .collect();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            // Skip the remaining request headers, noting whether a session cookie was sent
+            let mut line = String::new();
+            let mut has_session = false;
+            let mut content_length = 0;
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                let header = line.to_ascii_lowercase();
+                has_session |= header.starts_with("cookie:") && line.contains("session=");
+                if let Some(length) = header.strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap_or(0);
+                }
+                line.clear();
+            }
+            let mut request_body = vec![0; content_length];
+            if reader.read_exact(&mut request_body).is_err() {
+                continue;
+            }
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or("");
+            let path = parts.next().unwrap_or("");
+            let (status, content_type, body) = routes
+                .iter()
+                .find(|(route, ..)| *route == path)
+                .or_else(|| routes.iter().find(|(route, ..)| *route == "*"))
+                .map(|(_, status, content_type, body)| (*status, *content_type, body.as_str()))
+                .unwrap_or((404, "text/plain", "not found"));
+            let (location, body) = if (300..400).contains(&status) { (body, "") } else { ("", body) };
+            // Paths ending in "no-head" reject HEAD requests, like some misconfigured servers,
+            // and paths under /members/ need a session cookie, which posting pass=secret to /login sets.
+            // Pages under /api/ declare their path without the query as canonical in a Link header.
+            let logged_in = method == "POST" && path == "/login" && String::from_utf8_lossy(&request_body).contains("pass=secret");
+            let (status, body, location) = if method == "HEAD" && path.ends_with("no-head") {
+                (405, "", location)
+            } else if path.starts_with("/members/") && !has_session {
+                (403, "", location)
+            } else if method == "POST" && path == "/login" {
+                if logged_in { (302, "", "/members/") } else { (401, "", "") }
+            } else {
+                (status, body, location)
+            };
+            let cookie_header = if logged_in { "Set-Cookie: session=abc123; Path=/; HttpOnly\r\n" } else { "" };
+            let link_header = match path.strip_prefix("/api/") {
+                Some(api_path) => format!("Link: </api/{}>; rel=\"canonical\"\r\n", api_path.split('?').next().unwrap_or_default()),
+                None => String::new(),
+            };
+            // A response to HEAD declares the length of the body it leaves out
+            let content_length = body.len();
+            let body = if method == "HEAD" { "" } else { body };
+            if path.ends_with("slow") {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            let location_header = if location.is_empty() { String::new() } else { format!("Location: {}\r\n", location) };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} X\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}{}Connection: close\r\n\r\n{}",
+                status,
+                content_type,
+                content_length,
+                location_header,
+                cookie_header,
+                link_header,
+                body
+            );
+        }
+    });
+    Url::parse(&format!("http://localhost:{}/", port)).unwrap()
+}
+
+/// Serve a single HTML page over HTTPS with a self-signed certificate for localhost
+fn serve_tls(body: &'static str) -> Url {
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let cert = CertificateDer::from(include_bytes!("fixtures/self_signed_cert.der").to_vec());
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(include_bytes!("fixtures/self_signed_key.der").to_vec()));
+    let config = Arc::new(
+        rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let connection = rustls::ServerConnection::new(config.clone()).unwrap();
+            let mut stream = rustls::StreamOwned::new(connection, stream);
+            // A client that rejects the certificate fails the handshake while the request is read
+            let mut reader = BufReader::new(&mut stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.conn.send_close_notify();
+            let _ = stream.flush();
+        }
+    });
+    Url::parse(&format!("https://localhost:{}/", port)).unwrap()
+}
+
+#[test]
+fn test_link_checker_finds_broken_links() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="good/">good</a> <a href="missing.html">missing</a> <img src="logo.png">"#),
+        ("/good/", 200, "text/html", r#"<a href="../">home</a>"#),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+
+    let mut bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    bad_urls.sort();
+    assert_eq!(
+        bad_urls,
+        vec![
+            start_url.join("logo.png").unwrap().as_str(),
+            start_url.join("missing.html").unwrap().as_str(),
+        ]
+    );
+    for bad_url in &results.bad_urls {
+        assert_eq!(bad_url.found_on, vec![start_url.to_string()]);
+        assert_eq!(bad_url.status, Some(404));
+        assert_eq!(bad_url.error_kind, ErrorKind::HttpStatus);
+    }
+    assert!(results.url_map.contains_key(start_url.join("good/").unwrap().as_str()));
+}
+
+#[test]
+fn test_link_checker_srcset() {
+    let start_url = serve(vec![
+        (
+            "/",
+            200,
+            "text/html",
+            r#"<img src="small.png" srcset="small.png 1x, large.png 2x">
+               <picture><source srcset="wide.webp 960w,missing.webp 1920w"></picture>"#,
+        ),
+        ("/small.png", 200, "image/png", "png"),
+        ("/large.png", 200, "image/png", "png"),
+        ("/wide.webp", 200, "image/webp", "webp"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("missing.webp").unwrap().as_str()]);
+    assert_eq!(results.checked_urls.len(), 5);
+}
+
+#[test]
+fn test_link_checker_check_mailto() {
+    let start_url = serve(vec![(
+        "/",
+        200,
+        "text/html",
+        r#"<a href="mailto:team@example.com">ok</a> <a href="mailto:user@example,com">typo</a>
+           <a href="tel:+1 555 0100">call</a> <a href="tel:+1-555-CALL">letters</a>"#,
+    )]);
+
+    let results = LinkChecker::new().threads(1).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(1).check_mailto(true).run(start_url.clone());
+    let bad_urls: Vec<(&str, &str, ErrorKind)> = results
+        .bad_urls
+        .iter()
+        .map(|bad_url| (bad_url.url.as_str(), bad_url.reason.as_str(), bad_url.error_kind))
+        .collect();
+    assert_eq!(
+        bad_urls,
+        vec![
+            ("mailto:user@example,com", "invalid mailto link", ErrorKind::InvalidContactLink),
+            ("tel:+1-555-CALL", "invalid tel link", ErrorKind::InvalidContactLink),
+        ]
+    );
+    assert!(results.bad_urls.iter().all(|bad_url| bad_url.found_on == vec![start_url.to_string()]));
+}
+
+#[test]
+fn test_link_checker_capture_headers() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="page/">page</a> <img src="logo.png"> <a href="missing/">missing</a>"#),
+        ("/page/", 200, "text/html", "page"),
+        ("/logo.png", 200, "image/png", "png"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.headers.is_empty());
+
+    let results = LinkChecker::new().threads(2).capture_headers(true).run(start_url.clone());
+    let mut urls: Vec<&str> = results.headers.keys().map(String::as_str).collect();
+    urls.sort();
+    assert_eq!(
+        urls,
+        vec![
+            start_url.as_str(),
+            start_url.join("logo.png").unwrap().as_str(),
+            start_url.join("page/").unwrap().as_str(),
+        ]
+    );
+    let logo_headers = &results.headers[start_url.join("logo.png").unwrap().as_str()];
+    assert_eq!(logo_headers.get("content-type").map(String::as_str), Some("image/png"));
+    assert_eq!(logo_headers.get("connection").map(String::as_str), Some("close"));
+}
+
+#[test]
+fn test_link_checker_detect_duplicates() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="b/">b</a> <a href="c/">c</a> <a href="d/">d</a>"#),
+        ("/a/", 200, "text/html", "<p>Same page</p>"),
+        ("/b/", 200, "text/html", "<p>Same page</p>"),
+        ("/c/", 200, "text/html", "\n  <p>Same\n  page</p>\n"),
+        ("/d/", 200, "text/html", "<p>Other page</p>"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.duplicate_pages().is_empty());
+
+    let results = LinkChecker::new().threads(2).detect_duplicates(true).run(start_url.clone());
+    let same = [
+        start_url.join("a/").unwrap().to_string(),
+        start_url.join("b/").unwrap().to_string(),
+        start_url.join("c/").unwrap().to_string(),
+    ];
+    assert_eq!(results.duplicate_pages(), vec![same.iter().map(String::as_str).collect::<Vec<_>>()]);
+}
+
+#[test]
+fn test_link_checker_broken_by_source() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="missing-1/">1</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../missing-2/">2</a> <a href="../missing-3/">3</a>"#),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    let broken_by_source: Vec<(&str, Vec<&str>)> = results
+        .broken_by_source()
+        .into_iter()
+        .map(|(page, bad_urls)| {
+            let mut urls: Vec<&str> = bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+            urls.sort();
+            (page, urls)
+        })
+        .collect();
+    let url = |path: &str| start_url.join(path).unwrap().to_string();
+    assert_eq!(
+        broken_by_source,
+        vec![
+            (url("").as_str(), vec![url("missing-1/").as_str()]),
+            (url("a/").as_str(), vec![url("missing-2/").as_str(), url("missing-3/").as_str()]),
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_ip_address_host() {
+    let mut start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="page/">page</a> <a href="missing/">missing</a>"#),
+        ("/page/", 200, "text/html", ""),
+    ]);
+    start_url.set_host(Some("127.0.0.1")).unwrap();
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.url_map.contains_key(start_url.join("page/").unwrap().as_str()));
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("missing/").unwrap().as_str()]);
+}
+
+#[test]
+fn test_link_checker_broken_link_on_several_pages() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="b/">b</a> <a href="missing/">missing</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../missing/">missing</a>"#),
+        ("/b/", 200, "text/html", r#"<a href="../missing/">missing</a>"#),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    let mut found_on = results.bad_urls[0].found_on.clone();
+    found_on.sort();
+    let url = |path: &str| start_url.join(path).unwrap().to_string();
+    assert_eq!(found_on, vec![url(""), url("a/"), url("b/")]);
+}
+
+#[test]
+fn test_link_checker_check_urls() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="never-crawled/">link</a>"#),
+        ("/page/", 200, "text/html", ""),
+    ]);
+    let url = |path: &str| start_url.join(path).unwrap();
+    let links = vec![
+        (url(""), None),
+        (url("page/"), Some(url(""))),
+        (url("missing/"), Some(url(""))),
+        (url("missing/#section"), Some(url("page/"))),
+        (Url::parse("https://example.invalid/").unwrap(), Some(url("page/"))),
+    ];
+
+    let results = LinkChecker::new().threads(2).skip(Regex::new("example\\.invalid").unwrap()).check_urls(links);
+    // Links are checked once each and never crawled
+    assert_eq!(results.checked_urls.len(), 3);
+    assert!(results.url_map.is_empty());
+    let bad_urls: Vec<(&str, &[String])> = results.bad_urls.iter().map(|bad_url| (bad_url.url.as_str(), bad_url.found_on.as_slice())).collect();
+    assert_eq!(bad_urls, vec![(url("missing/").as_str(), [url("").to_string(), url("page/").to_string()].as_slice())]);
+}
+
+#[test]
+fn test_link_checker_content_type_mismatches() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="data">data</a> <a href="page/">page</a> <a href="report.pdf">report</a> <img src="logo.png">"#),
+        ("/data", 200, "application/json", "{}"),
+        ("/page/", 200, "text/html", ""),
+        ("/report.pdf", 200, "text/html; charset=utf-8", ""),
+        ("/logo.png", 200, "image/png", ""),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.content_type_mismatches.is_empty());
+
+    // Without trailing slashes added, /data is requested as is
+    let results = LinkChecker::new().threads(2).add_trailing_slashes(false).report_mismatches(true).run(start_url.clone());
+    let mut mismatches = results.content_type_mismatches.clone();
+    mismatches.sort_by(|a, b| a.url.cmp(&b.url));
+    assert_eq!(
+        mismatches,
+        vec![
+            ContentTypeMismatch {
+                url: start_url.join("data").unwrap().to_string(),
+                guessed_html: true,
+                content_type: "application/json".to_string(),
+            },
+            ContentTypeMismatch {
+                url: start_url.join("report.pdf").unwrap().to_string(),
+                guessed_html: false,
+                content_type: "text/html; charset=utf-8".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_same_scheme() {
+    // Nothing listens on port 1, so the https:// link fails quickly instead of reaching the plain HTTP server
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="https://localhost:1/secure/">secure</a> <a href="page/">page</a>"#),
+        ("/page/", 200, "text/html", ""),
+    ]);
+    let secure_url = "https://localhost:1/secure/";
+
+    // Absolute links on the site are only checked when asked for
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.checked_urls.iter().all(|checked| checked.url != secure_url));
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).same_scheme(true).run(start_url.clone());
+    assert!(results.url_map.contains_key(start_url.join("page/").unwrap().as_str()));
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].url, secure_url);
+    assert_eq!(results.bad_urls[0].found_on, vec![start_url.to_string()]);
+    assert!(results.mixed_content.is_empty());
+}
+
+#[test]
+fn test_link_checker_max_retries() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="error/">error</a>"#),
+        ("/error/", 500, "text/html", ""),
+    ]);
+
+    // With retrying disabled, the long retry delay is never waited for
+    let start = std::time::Instant::now();
+    let results = LinkChecker::new()
+        .threads(2)
+        .max_retries(0)
+        .retry_base_delay(std::time::Duration::from_secs(60))
+        .run(start_url.clone());
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].status, Some(500));
+    assert_eq!(results.requests, RequestCounts { head: 0, get: 2, retries: 0 });
+
+    // Two retries wait 50ms and then 100ms
+    let start = std::time::Instant::now();
+    let results = LinkChecker::new()
+        .threads(2)
+        .max_retries(2)
+        .retry_base_delay(std::time::Duration::from_millis(50))
+        .run(start_url.clone());
+    assert!(start.elapsed() >= std::time::Duration::from_millis(150));
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.requests, RequestCounts { head: 0, get: 4, retries: 2 });
+}
+
+#[test]
+fn test_link_checker_adaptive() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a><a href="b/">b</a><a href="c/">c</a><a href="error/">error</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="missing/">missing</a>"#),
+        ("/b/", 200, "text/html", "<p>B</p>"),
+        ("/c/", 200, "text/html", "<p>C</p>"),
+        ("/error/", 503, "text/html", ""),
+    ]);
+
+    // Server errors shrink the concurrency down to a single request at a time, which must not
+    // stall the crawl or change what it finds
+    let results = LinkChecker::new()
+        .threads(4)
+        .adaptive(true)
+        .max_retries(3)
+        .retry_base_delay(std::time::Duration::from_millis(10))
+        .run(start_url);
+    let mut broken: Vec<_> = results.bad_urls.iter().map(|bad_url| (bad_url.url.rsplit('/').nth(1).unwrap(), bad_url.status)).collect();
+    broken.sort();
+    assert_eq!(broken, vec![("error", Some(503)), ("missing", Some(404))]);
+    assert_eq!(results.requests.retries, 3);
+}
+
+#[test]
+fn test_link_checker_thread_metrics() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a><a href="b/">b</a><a href="slow">slow</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="missing/">missing</a>"#),
+        ("/b/", 200, "text/html", "<p>B</p>"),
+        ("/slow", 200, "text/plain", "slow"),
+    ]);
+
+    let results = LinkChecker::new().threads(3).add_trailing_slashes(false).run(start_url.clone());
+    assert!(results.thread_metrics.is_empty());
+
+    let results = LinkChecker::new().threads(3).add_trailing_slashes(false).thread_metrics(true).run(start_url);
+    assert_eq!(results.thread_metrics.len(), 3);
+    let requests: usize = results.thread_metrics.iter().map(|metrics| metrics.requests).sum();
+    assert_eq!(requests, results.checked_urls.len());
+    // The slow URL takes 200ms, all of it on the request
+    assert!(results.thread_metrics.iter().any(|metrics| metrics.request_ms >= 200));
+}
+
+#[test]
+fn test_link_checker_request_counts() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="old/">old</a><img src="logo.png">"#),
+        ("/old/", 301, "text/html", "/new/"),
+        ("/new/", 200, "text/html", "<p>New</p>"),
+        ("/logo.png", 200, "image/png", ""),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url);
+    // The redirect is followed with a second request, and the image is only checked with HEAD
+    assert_eq!(results.requests, RequestCounts { head: 1, get: 3, retries: 0 });
+    let stats = CrawlStats::new(&results, std::time::Duration::from_millis(10), false);
+    assert_eq!((stats.total_requests, stats.head_requests, stats.get_requests), (4, 1, 3));
+}
+
+#[test]
+fn test_link_checker_dry_run() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="missing/">missing</a>"#),
+        ("/sitemap.xml", 200, "application/xml", r#"<urlset><url><loc>http://localhost:{port}/gone/</loc></url></urlset>"#),
+    ]);
+
+    let results = LinkChecker::new().threads(2).sitemap(true).detect_soft_404(true).dry_run(true).run(start_url.clone());
+    // Not even the start page is fetched, so no links are found on it
+    assert_eq!(results.requests, RequestCounts { head: 0, get: 0, retries: 0 });
+    assert!(results.bad_urls.is_empty());
+    let checked: Vec<&str> = results.checked_urls.iter().map(|checked| checked.url.as_str()).collect();
+    assert_eq!(checked, vec![start_url.as_str()]);
+    assert_eq!(results.url_map[start_url.as_str()], Vec::<String>::new());
+}
+
+#[test]
+fn test_link_checker_count_head_content_length() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<img src="photo.jpg"><a href="video.mp4">video</a>"#.to_string()),
+        ("/photo.jpg", 200, "image/jpeg", "x".repeat(1000)),
+        ("/video.mp4", 200, "video/mp4", "x".repeat(5000)),
+    ]);
+    let page_size = r#"<img src="photo.jpg"><a href="video.mp4">video</a>"#.len();
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert_eq!(results.requests.head, 2);
+    assert_eq!((results.bytes_downloaded, results.estimated_bytes), (page_size, 0));
+
+    // Only the media checked with HEAD is estimated, the page itself was downloaded
+    let results = LinkChecker::new().threads(2).count_head_content_length(true).run(start_url);
+    assert_eq!((results.bytes_downloaded, results.estimated_bytes), (page_size, 6000));
+    let stats = CrawlStats::new(&results, std::time::Duration::from_millis(10), false);
+    assert_eq!(stats.estimated_bytes, 6000);
+}
+
+#[test]
+fn test_link_checker_trust_host() {
+    // Nothing listens on port 1, so the external link is broken when it is checked
+    let start_url = serve(vec![("/", 200, "text/html", r#"<a href="http://127.0.0.1:1/profile">profile</a>"#)]);
+
+    let results = LinkChecker::new().threads(2).check_external(true).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].error_kind, ErrorKind::Connection);
+
+    let results = LinkChecker::new().threads(2).check_external(true).trust_hosts(["127.0.0.1"]).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+    assert_eq!(results.checked_urls.len(), 1);
+}
+
+#[test]
+fn test_link_checker_external_allow_and_deny() {
+    // Nothing listens on port 1, so both external links are broken when they are checked
+    let start_url = serve(vec![(
+        "/",
+        200,
+        "text/html",
+        r#"<a href="http://127.0.0.1:1/profile">profile</a><a href="http://127.0.0.2:1/partner">partner</a>"#,
+    )]);
+    let broken_hosts = |results: &UrlResults| -> Vec<String> {
+        let mut hosts: Vec<String> = results.bad_urls.iter().map(|bad_url| Url::parse(&bad_url.url).unwrap().host_str().unwrap().to_string()).collect();
+        hosts.sort();
+        hosts
+    };
+
+    let results = LinkChecker::new().threads(2).max_retries(0).check_external(true).run(start_url.clone());
+    assert_eq!(broken_hosts(&results), vec!["127.0.0.1", "127.0.0.2"]);
+
+    let results = LinkChecker::new().threads(2).max_retries(0).check_external(true).external_deny(["127.0.0.1"]).run(start_url.clone());
+    assert_eq!(broken_hosts(&results), vec!["127.0.0.2"]);
+    assert_eq!(results.checked_urls.len(), 2);
+
+    let results = LinkChecker::new().threads(2).max_retries(0).check_external(true).external_allow(["127.0.0.1"]).run(start_url.clone());
+    assert_eq!(broken_hosts(&results), vec!["127.0.0.1"]);
+}
+
+#[test]
+fn test_link_checker_ignore_cert() {
+    let start_url = serve_tls("<p>staging</p>");
+
+    let results = LinkChecker::new().threads(1).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].error_kind, ErrorKind::Tls);
+
+    let results = LinkChecker::new().threads(1).ignore_cert(true).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+    assert_eq!(results.checked_urls.len(), 1);
+    assert_eq!(results.checked_urls[0].status, Some(200));
+}
+
+#[test]
+fn test_link_checker_orphan_pages() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="guides/">guides</a> <a href="blog/">blog</a>"#),
+        ("/guides/", 200, "text/html", r#"<a href="/blog/#latest">blog</a> <a href="/">home</a>"#),
+        ("/blog/", 200, "text/html", ""),
+        ("/unlisted/", 200, "text/html", r#"<a href="/guides/">guides</a>"#),
+    ]);
+    let unlisted = start_url.join("unlisted/").unwrap();
+
+    let results = LinkChecker::new().threads(2).run_seeds(vec![start_url.clone(), unlisted.clone()]);
+    assert_eq!(results.orphan_pages(), vec![unlisted.as_str()]);
+    assert_eq!(
+        results.most_linked_pages(2),
+        vec![(start_url.join("blog/").unwrap().as_str(), 2), (start_url.join("guides/").unwrap().as_str(), 2)]
+    );
+}
+
+#[test]
+fn test_link_checker_wait_on_429() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+
+    // The first request is rate limited with a Retry-After of one second, later ones succeed
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let start_url = Url::parse(&format!("http://localhost:{}/", listener.local_addr().unwrap().port())).unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let response = if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                "HTTP/1.1 429 X\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            } else {
+                "HTTP/1.1 200 X\r\nContent-Type: text/html\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    let results = LinkChecker::new().threads(1).ignore_robots(true).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].status, Some(429));
+
+    requests.store(0, Ordering::SeqCst);
+    let start = std::time::Instant::now();
+    let results = LinkChecker::new().threads(1).ignore_robots(true).wait_on_429(true).run(start_url);
+    assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+    assert!(results.bad_urls.is_empty());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_link_checker_compressed_pages() {
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn compress<W: Write>(mut encoder: W, html: &str) -> W {
+        encoder.write_all(html.as_bytes()).unwrap();
+        encoder
+    }
+    let gzip = compress(GzEncoder::new(Vec::new(), Compression::default()), r#"<a href="deflate/">deflate</a><a href="missing.html">missing</a>"#)
+        .finish()
+        .unwrap();
+    let deflate = compress(ZlibEncoder::new(Vec::new(), Compression::default()), r#"<a href="gone.html">gone</a>"#)
+        .finish()
+        .unwrap();
+
+    // The start page is gzip encoded and links to a deflate encoded page, when the request accepts them
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let start_url = Url::parse(&format!("http://localhost:{}/", listener.local_addr().unwrap().port())).unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while reader.read_line(&mut request).is_ok_and(|n| n > 2) {}
+            let request = request.to_ascii_lowercase();
+            let accepts = request.contains("accept-encoding: gzip, deflate");
+            let (encoding, body) = match request.split_whitespace().nth(1) {
+                Some("/") if accepts => ("gzip", gzip.as_slice()),
+                Some("/deflate/") if accepts => ("deflate", deflate.as_slice()),
+                _ => {
+                    let _ = stream.write_all(b"HTTP/1.1 404 X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                    continue;
+                }
+            };
+            let headers = format!(
+                "HTTP/1.1 200 X\r\nContent-Type: text/html\r\nContent-Encoding: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                encoding,
+                body.len()
+            );
+            let _ = stream.write_all(headers.as_bytes());
+            let _ = stream.write_all(body);
+        }
+    });
+
+    let results = LinkChecker::new().threads(2).ignore_robots(true).run(start_url.clone());
+    assert_eq!(results.url_map[start_url.as_str()].len(), 2);
+    let mut broken: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    broken.sort();
+    assert_eq!(broken, vec![start_url.join("deflate/gone.html").unwrap().as_str(), start_url.join("missing.html").unwrap().as_str()]);
+}
+
+#[test]
+fn test_link_checker_head_only() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="linked/">linked</a>"#),
+        ("/legacy-no-head", 200, "text/html", r#"<a href="linked/">linked</a>"#),
+        ("/gone/", 404, "text/html", ""),
+    ]);
+    let seeds = ["/", "/legacy-no-head", "/gone/"].map(|path| start_url.join(path).unwrap());
+
+    // Only the seeds are checked, and only with HEAD unless the server rejects it
+    let results = LinkChecker::new().threads(2).head_only(true).run_seeds(seeds.to_vec());
+    assert_eq!(results.checked_urls.len(), 3);
+    assert!(results.url_map.is_empty());
+    assert_eq!(results.requests, RequestCounts { head: 3, get: 1, retries: 0 });
+    assert_eq!(results.bytes_downloaded, 0);
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/gone/"));
+}
+
+#[test]
+fn test_link_checker_include_assets_in_scope() {
+    let start_url = serve(vec![
+        (
+            "/",
+            200,
+            "text/html",
+            r#"<a href="api/users">users</a><a href="api/orders">orders</a><a href="api/stats">stats</a><a href="about">about</a><a href="contact.html">contact</a>"#,
+        ),
+        ("/api/users", 200, "application/json", r#"{"users": []}"#),
+        ("/api/orders", 200, "application/json", r#"{"orders": []}"#),
+        ("/api/stats", 200, "application/json", r#"{"stats": {}}"#),
+        ("/about", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+        ("/contact.html", 200, "text/html", "contact"),
+    ]);
+    let checker = || LinkChecker::new().threads(2).add_trailing_slashes(false);
+
+    // Every extensionless URL is guessed to be HTML and downloaded
+    let results = checker().run(start_url.clone());
+    assert_eq!(results.requests, RequestCounts { head: 0, get: 7, retries: 0 });
+    assert_eq!(results.bad_urls.len(), 1);
+
+    // The JSON endpoints are only checked with HEAD, while the extensionless HTML page costs an
+    // extra HEAD and pages with an HTML extension are still downloaded straight away
+    let results = checker().include_assets_in_scope(true).run(start_url.clone());
+    assert_eq!(results.requests, RequestCounts { head: 5, get: 4, retries: 0 });
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/missing.html"));
+}
+
+#[test]
+fn test_link_checker_path_prefixes() {
+    let start_url = serve(vec![
+        ("/docs/", 200, "text/html", r#"<a href="/blog/post/">post</a><a href="/about/">about</a>"#),
+        ("/blog/post/", 200, "text/html", r#"<a href="../missing.html">missing</a>"#),
+        ("/about/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ])
+    .join("/docs/")
+    .unwrap();
+
+    let results = LinkChecker::new().threads(2).path_prefixes(["/blog/"]).run(start_url.clone());
+    let mut pages: Vec<&str> = results.url_map.keys().map(|page| page.as_str()).collect();
+    pages.sort();
+    // The page under /about/ is out of scope, so it and its broken link are never checked
+    assert_eq!(pages.len(), 2);
+    assert!(pages[0].ends_with("/blog/post/"));
+    assert!(pages[1].ends_with("/docs/"));
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/blog/missing.html"));
+}
+
+#[test]
+fn test_link_checker_meta_refresh() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="old/">old</a>"#),
+        ("/old/", 200, "text/html", r#"<meta http-equiv="refresh" content="0; url=/new/">"#),
+        ("/new/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+    let old = start_url.join("old/").unwrap().to_string();
+    let new = start_url.join("new/").unwrap().to_string();
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert_eq!(results.meta_refreshes, vec![MetaRefresh { url: old.clone(), target: new.clone() }]);
+    assert!(results.url_map[&old].is_empty());
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).follow_meta_refresh(true).run(start_url.clone());
+    assert_eq!(results.meta_refreshes.len(), 1);
+    assert_eq!(results.url_map[&old], vec![new]);
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/missing.html"));
+}
+
+#[test]
+fn test_link_checker_check_forms() {
+    let start_url = serve(vec![
+        (
+            "/",
+            200,
+            "text/html",
+            r##"<form action="subscribe/"></form><form action="/old-handler"></form><form action=""></form><form action="#top"></form>"##,
+        ),
+        ("/subscribe/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+    let subscribe = start_url.join("subscribe/").unwrap().to_string();
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+    assert!(results.url_map[start_url.as_str()].is_empty());
+
+    // The handler is only checked, not crawled, so the broken link on it isn't found
+    let results = LinkChecker::new().threads(2).check_forms(true).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/old-handler"));
+    assert_eq!(results.bad_urls[0].found_on, vec![start_url.to_string()]);
+    assert!(results.url_map.get(&subscribe).is_none_or(Vec::is_empty));
+    assert_eq!(results.requests, RequestCounts { head: 2, get: 1, retries: 0 });
+}
+
+#[test]
+fn test_link_checker_collect_anchors() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r##"<h2 id="intro">Intro</h2><a href="guide/#usage">usage</a><a href="guide/#nowhere">nowhere</a>"##),
+        ("/guide/", 200, "text/html", r#"<h1 id="top-title">Guide</h1><a name="setup"></a><h2 id="usage">Usage</h2><p id="usage">Again</p>"#),
+    ]);
+    let guide = start_url.join("guide/").unwrap().to_string();
+
+    let results = LinkChecker::new().threads(2).check_anchors(true).run(start_url.clone());
+    assert!(results.anchors.is_empty());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].error_kind, ErrorKind::MissingAnchor);
+
+    // Anchors are listed in document order, once each
+    let results = LinkChecker::new().threads(2).collect_anchors(true).run(start_url.clone());
+    assert_eq!(results.anchors[start_url.as_str()], vec!["intro"]);
+    assert_eq!(results.anchors[&guide], vec!["top-title", "setup", "usage"]);
+}
+
+#[test]
+fn test_link_checker_max_body_size() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="big/">big</a> <a href="data.bin">data</a>"#.to_string()),
+        ("/big/", 200, "text/html", "x".repeat(5_000)),
+        ("/data.bin", 200, "application/octet-stream", "x".repeat(5_000)),
+    ]);
+
+    let results = LinkChecker::new().threads(2).max_body_size(1_000).no_head(true).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].url, start_url.join("big/").unwrap().to_string());
+    assert_eq!(results.bad_urls[0].error_kind, ErrorKind::TooLarge);
+    assert_eq!(results.bad_urls[0].reason, "response too large: more than 1000 bytes");
+    assert!(results.bytes_downloaded < 1_000);
+
+    let results = LinkChecker::new().threads(2).run(start_url);
+    assert!(results.bad_urls.is_empty());
+}
+
+#[test]
+fn test_link_checker_offsite_redirects() {
+    // The server is reached as localhost, so a redirect to 127.0.0.1 leaves the crawl's scope
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="moved/">moved</a>"#),
+        ("/moved/", 301, "text/html", "http://127.0.0.1:{port}/elsewhere/"),
+        ("/elsewhere/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+    let moved = start_url.join("moved/").unwrap().to_string();
+
+    let results = LinkChecker::new().threads(2).check_external(true).run(start_url.clone());
+    assert!(results.url_map[&moved].is_empty());
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).check_external(true).allow_offsite_redirects(true).run(start_url.clone());
+    assert_eq!(results.url_map[&moved].len(), 1);
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/elsewhere/missing.html"));
+}
+
+#[test]
+fn test_link_checker_include_pattern() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="guides/">guides</a> <a href="blog/">blog</a>"#),
+        ("/guides/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+        ("/blog/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .include(Regex::new("/guides/").unwrap())
+        .run(start_url.clone());
+
+    assert!(results.url_map.contains_key(start_url.as_str()));
+    assert!(results.url_map.contains_key(start_url.join("guides/").unwrap().as_str()));
+    assert!(!results.url_map.contains_key(start_url.join("blog/").unwrap().as_str()));
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("guides/missing.html").unwrap().as_str()]);
+}
+
+#[test]
+fn test_link_checker_check_external() {
+    let external_url = serve(vec![
+        ("/ok", 200, "text/html", r#"<a href="/never-crawled">link</a>"#),
+    ]);
+    // Address the external server by IP so it is a different host from the crawled site
+    let external_base = format!("http://127.0.0.1:{}", external_url.port().unwrap());
+
+    let page = format!(
+        r#"<a href="{0}/ok">ok</a> <a href="{0}/gone">gone</a> <a href="mailto:someone@example.com">mail</a>"#,
+        external_base
+    );
+    let start_url = serve(vec![("/", 200, "text/html", page)]);
+
+    let without_external = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(without_external.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).check_external(true).run(start_url.clone());
+    let bad_urls: Vec<(&str, &[String])> = results
+        .bad_urls
+        .iter()
+        .map(|bad_url| (bad_url.url.as_str(), bad_url.found_on.as_slice()))
+        .collect();
+    let gone = format!("{}/gone", external_base);
+    assert_eq!(bad_urls, vec![(gone.as_str(), [start_url.to_string()].as_slice())]);
+    // External pages are checked but their links are never followed
+    assert!(!results.url_map.keys().any(|page| page.contains("never-crawled")));
+    assert!(!results.url_map.values().flatten().any(|link| link.contains("never-crawled")));
+}
+
+#[test]
+fn test_link_checker_nofollow() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="private/" rel="nofollow">private</a> <a href="listing/">listing</a>"#),
+        ("/private/", 200, "text/html", r#"<a href="missing-private.html">missing</a>"#),
+        (
+            "/listing/",
+            200,
+            "text/html",
+            r#"<meta name="robots" content="noindex, nofollow"><a href="missing-listing.html">missing</a>"#,
+        ),
+    ]);
+
+    // Nofollow pages are checked, but their links are not
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+    assert!(results.checked_urls.iter().any(|checked| checked.url == start_url.join("private/").unwrap().as_str()));
+
+    let mut bad_urls: Vec<String> = LinkChecker::new()
+        .threads(2)
+        .ignore_nofollow(true)
+        .run(start_url.clone())
+        .bad_urls
+        .into_iter()
+        .map(|bad_url| bad_url.url)
+        .collect();
+    bad_urls.sort();
+    assert_eq!(
+        bad_urls,
+        vec![
+            start_url.join("listing/missing-listing.html").unwrap().to_string(),
+            start_url.join("private/missing-private.html").unwrap().to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_max_pages() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="missing/">missing</a> <a href="a/">a</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../b/">b</a>"#),
+        ("/b/", 200, "text/html", r#"<a href="../c/">c</a>"#),
+        ("/c/", 200, "text/html", ""),
+    ]);
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+
+    let results = LinkChecker::new()
+        .threads(1)
+        .max_pages(Some(2))
+        .shutdown_flag(shutdown_flag.clone())
+        .run(start_url.clone());
+
+    // The broken link doesn't count as a crawled page
+    assert_eq!(results.checked_urls.len(), 3);
+    assert_eq!(results.url_map.len(), 2);
+    assert!(!results.url_map.contains_key(start_url.join("b/").unwrap().as_str()));
+    // A crawl cut short by a limit is reported as interrupted
+    assert!(shutdown_flag.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_link_checker_host_failure_threshold() {
+    // Nothing listens on port 1, so the external host is down
+    let links: String = (1..=5).map(|i| format!(r#"<a href="http://127.0.0.1:1/page{}.png">page</a>"#, i)).collect();
+    let start_url = serve(vec![("/", 200, "text/html", links)]);
+    let checker = || LinkChecker::new().threads(1).max_retries(0).check_external(true);
+
+    let results = checker().run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 5);
+    assert_eq!(results.requests.head, 5);
+
+    // After two failed requests, the rest of the host's URLs are broken without requesting them
+    let results = checker().host_failure_threshold(Some(2)).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 5);
+    assert_eq!(results.requests.head, 2);
+    let unreachable = results.bad_urls.iter().filter(|bad_url| bad_url.reason.starts_with("host unreachable")).count();
+    assert_eq!(unreachable, 3);
+    assert!(results.bad_urls.iter().all(|bad_url| bad_url.error_kind == ErrorKind::Connection));
+}
+
+#[test]
+fn test_link_checker_crawl_delay_from_robots() {
+    let start_url = serve(vec![
+        ("/robots.txt", 200, "text/plain", "User-agent: *\nCrawl-delay: 0.15\n"),
+        ("/", 200, "text/html", r#"<a href="a/">a</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../b/">b</a>"#),
+        ("/b/", 200, "text/html", ""),
+    ]);
+
+    let results = LinkChecker::new().threads(1).run(start_url.clone());
+    assert_eq!(results.checked_urls.len(), 3);
+
+    // The thread waits after the first two pages before taking the next one
+    let start = std::time::Instant::now();
+    let results = LinkChecker::new().threads(1).crawl_delay_from_robots(true).run(start_url.clone());
+    assert_eq!(results.checked_urls.len(), 3);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(300));
+}
+
+#[test]
+fn test_link_checker_max_errors() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="gone1.html">gone</a><a href="a/">a</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="gone2.html">gone</a><a href="../b/">b</a>"#),
+        ("/b/", 200, "text/html", r#"<a href="gone3.html">gone</a>"#),
+    ]);
+
+    let results = LinkChecker::new().threads(1).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 3);
+
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    let results = LinkChecker::new()
+        .threads(1)
+        .max_errors(Some(1))
+        .shutdown_flag(shutdown_flag.clone())
+        .run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/gone1.html"));
+    assert!(!results.url_map.contains_key(start_url.join("b/").unwrap().as_str()));
+    assert!(shutdown_flag.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_link_checker_sitemap() {
+    let start_url = serve(vec![
+        ("/docs/", 200, "text/html", ""),
+        (
+            "/sitemap.xml",
+            200,
+            "application/xml",
+            r#"<sitemapindex><sitemap><loc>http://localhost:{port}/sitemap-docs.xml</loc></sitemap></sitemapindex>"#,
+        ),
+        (
+            "/sitemap-docs.xml",
+            200,
+            "application/xml",
+            r#"<urlset>
+                <url><loc>http://localhost:{port}/docs/</loc></url>
+                <url><loc>http://localhost:{port}/docs/orphan/</loc></url>
+                <url><loc>http://localhost:{port}/docs/gone/</loc></url>
+                <url><loc>http://localhost:{port}/blog/</loc></url>
+            </urlset>"#,
+        ),
+        ("/docs/orphan/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+
+    let without_sitemap = LinkChecker::new().threads(2).run(start_url.join("docs/").unwrap());
+    assert!(without_sitemap.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).sitemap(true).run(start_url.join("docs/").unwrap());
+    let mut bad_urls: Vec<(&str, &[String])> =
+        results.bad_urls.iter().map(|bad_url| (bad_url.url.as_str(), bad_url.found_on.as_slice())).collect();
+    bad_urls.sort();
+    // A broken page of the sitemap was found on the sitemap that lists it
+    let sitemap_url = start_url.join("sitemap-docs.xml").unwrap().to_string();
+    assert_eq!(
+        bad_urls,
+        vec![
+            (start_url.join("docs/gone/").unwrap().as_str(), &[sitemap_url][..]),
+            (start_url.join("docs/orphan/missing.html").unwrap().as_str(), &[start_url.join("docs/orphan/").unwrap().to_string()][..]),
+        ]
+    );
+    // Sitemap pages outside the path prefix are not checked
+    assert!(!results.checked_urls.iter().any(|checked| checked.url.contains("/blog/")));
+}
+
+#[test]
+fn test_link_checker_sitemap_since() {
+    let start_url = serve(vec![
+        (
+            "/sitemap.xml",
+            200,
+            "application/xml",
+            r#"<sitemapindex>
+                <sitemap><loc>http://localhost:{port}/sitemap-2023.xml</loc><lastmod>2023-12-31</lastmod></sitemap>
+                <sitemap><loc>http://localhost:{port}/sitemap-2024.xml</loc><lastmod>2024-06-02T08:00:00Z</lastmod></sitemap>
+            </sitemapindex>"#,
+        ),
+        ("/sitemap-2023.xml", 200, "application/xml", r#"<urlset><url><loc>http://localhost:{port}/archived/</loc></url></urlset>"#),
+        (
+            "/sitemap-2024.xml",
+            200,
+            "application/xml",
+            r#"<urlset>
+                <url><loc>http://localhost:{port}/</loc><lastmod>2024-06-02</lastmod></url>
+                <url><loc>http://localhost:{port}/changed/</loc><lastmod>2024-05-01T03:00:00+02:00</lastmod></url>
+                <url><loc>http://localhost:{port}/unchanged/</loc><lastmod>2024-05-01T01:00:00+02:00</lastmod></url>
+                <url><loc>http://localhost:{port}/undated/</loc></url>
+            </urlset>"#,
+        ),
+        ("/", 200, "text/html", r#"<a href="changed/">changed</a> <a href="unchanged/">unchanged</a>"#),
+        ("/changed/", 200, "text/html", r#"<a href="../deep/">deep</a> <a href="../missing/">missing</a>"#),
+        ("/deep/", 200, "text/html", r#"<a href="../missing-deep/">missing</a>"#),
+        ("/unchanged/", 200, "text/html", r#"<a href="../missing-unchanged/">missing</a>"#),
+        ("/undated/", 200, "text/html", ""),
+    ]);
+    let since = link_checker::sitemap::parse_lastmod("2024-05-01").unwrap();
+
+    let results = LinkChecker::new().threads(2).sitemap_since(Some(since)).run(start_url.clone());
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("missing/").unwrap().as_str()]);
+    // The changed pages are crawled, and the pages they link to only checked
+    let links_on = |page: &str| results.url_map[start_url.join(page).unwrap().as_str()].len();
+    assert_eq!((links_on(""), links_on("changed/"), links_on("undated/")), (2, 2, 0));
+    assert_eq!((links_on("unchanged/"), links_on("deep/")), (0, 0));
+    // The old sitemap isn't fetched
+    assert!(!results.checked_urls.iter().any(|checked| checked.url.contains("/archived/")));
+
+    // Unless a depth allows more, here the pages linked from the changed ones
+    let results = LinkChecker::new().threads(2).sitemap_since(Some(since)).max_depth(Some(1)).run(start_url.clone());
+    let mut bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    bad_urls.sort();
+    assert_eq!(
+        bad_urls,
+        vec![
+            start_url.join("missing-deep/").unwrap().as_str(),
+            start_url.join("missing-unchanged/").unwrap().as_str(),
+            start_url.join("missing/").unwrap().as_str(),
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_redirects() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="old/">old</a> <a href="loop-a/">loop</a> <a href="image.png">image</a>"#),
+        ("/old/", 301, "text/html", "/moved/"),
+        ("/moved/", 302, "text/html", "/new/"),
+        ("/new/", 200, "text/html", ""),
+        ("/loop-a/", 302, "text/html", "/loop-b/"),
+        ("/loop-b/", 302, "text/html", "/loop-a/"),
+        ("/image.png", 307, "image/png", "/assets/image.png"),
+        ("/assets/image.png", 200, "image/png", ""),
+    ]);
+
+    let results = LinkChecker::new().threads(2).max_redirects(5).run(start_url.clone());
+
+    let bad_urls: Vec<(&str, ErrorKind)> = results
+        .bad_urls
+        .iter()
+        .map(|bad_url| (bad_url.url.as_str(), bad_url.error_kind))
+        .collect();
+    assert_eq!(bad_urls, vec![(start_url.join("loop-a/").unwrap().as_str(), ErrorKind::TooManyRedirects)]);
+
+    let mut redirects: Vec<(&str, &str, Vec<u16>)> = results
+        .redirects
+        .iter()
+        .map(|redirect| {
+            let statuses = redirect.hops.iter().map(|hop| hop.status).collect();
+            (redirect.url.as_str(), redirect.destination.as_str(), statuses)
+        })
+        .collect();
+    redirects.sort();
+    assert_eq!(
+        redirects,
+        vec![
+            (start_url.join("image.png").unwrap().as_str(), start_url.join("assets/image.png").unwrap().as_str(), vec![307]),
+            (start_url.join("old/").unwrap().as_str(), start_url.join("new/").unwrap().as_str(), vec![301, 302]),
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_head_not_allowed() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<img src="image-no-head"> <img src="missing-no-head">"#),
+        ("/image-no-head", 200, "image/png", "png"),
+    ]);
+
+    for no_head in [false, true] {
+        let results = LinkChecker::new().threads(2).no_head(no_head).run(start_url.clone());
+        let bad_urls: Vec<(&str, Option<u16>)> = results
+            .bad_urls
+            .iter()
+            .map(|bad_url| (bad_url.url.as_str(), bad_url.status))
+            .collect();
+        assert_eq!(bad_urls, vec![(start_url.join("missing-no-head").unwrap().as_str(), Some(404))]);
+    }
+}
+
+#[test]
+fn test_link_checker_accept_and_fail_status() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="login-required/">login</a> <a href="moved/">moved</a> <a href="missing/">missing</a>"#),
+        ("/login-required/", 401, "text/html", ""),
+        ("/moved/", 301, "text/html", "/new/"),
+        ("/new/", 200, "text/html", "new"),
+    ]);
+
+    let bad_urls = |results: &link_checker::UrlResults| {
+        let mut bad_urls: Vec<(String, Option<u16>)> = results
+            .bad_urls
+            .iter()
+            .map(|bad_url| (bad_url.url.clone(), bad_url.status))
+            .collect();
+        bad_urls.sort();
+        bad_urls
+    };
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert_eq!(
+        bad_urls(&results),
+        vec![
+            (start_url.join("login-required/").unwrap().to_string(), Some(401)),
+            (start_url.join("missing/").unwrap().to_string(), Some(404)),
+        ]
+    );
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .accept_status([401, 403])
+        .fail_status([301])
+        .run(start_url.clone());
+    assert_eq!(
+        bad_urls(&results),
+        vec![
+            (start_url.join("missing/").unwrap().to_string(), Some(404)),
+            (start_url.join("moved/").unwrap().to_string(), Some(301)),
+        ]
+    );
+}
+
+#[test]
+fn test_link_checker_link_text() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="report.pdf">Download the <em>PDF</em></a> <a href="about/#team">Our team</a> <img src="logo.png">"#),
+        ("/about/", 200, "text/html", "<h1>About</h1>"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).check_anchors(true).run(start_url.clone());
+    let link_text = |path: &str| {
+        let url = start_url.join(path).unwrap();
+        results.bad_urls.iter().find(|bad_url| bad_url.url == url.as_str()).unwrap().link_text.clone()
+    };
+    assert_eq!(results.bad_urls.len(), 3);
+    assert_eq!(link_text("report.pdf").as_deref(), Some("Download the PDF"));
+    assert_eq!(link_text("about/#team").as_deref(), Some("Our team"));
+    assert_eq!(link_text("logo.png"), None);
+
+    let json = serde_json::to_value(&results.bad_urls).unwrap();
+    assert!(json.as_array().unwrap().iter().any(|bad_url| bad_url["link_text"] == "Download the PDF"));
+    assert!(json.as_array().unwrap().iter().any(|bad_url| bad_url.get("link_text").is_none()));
+}
+
+#[test]
+fn test_link_checker_broken_on_redirect_to() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="deleted/">deleted</a> <a href="moved/">moved</a> <a href="signin/">sign in</a>"#),
+        ("/deleted/", 302, "text/html", "/signin/"),
+        ("/moved/", 301, "text/html", "/new/"),
+        ("/new/", 200, "text/html", "new"),
+        ("/signin/", 200, "text/html", "sign in"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .broken_on_redirect_to(Regex::new("/signin").unwrap())
+        .run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+    let bad_url = &results.bad_urls[0];
+    assert_eq!(bad_url.url, start_url.join("deleted/").unwrap().as_str());
+    assert_eq!(bad_url.error_kind, ErrorKind::BrokenRedirect);
+    assert_eq!(bad_url.reason, format!("redirected to {}", start_url.join("signin/").unwrap()));
+    assert_eq!(bad_url.found_on, vec![start_url.to_string()]);
+}
+
+#[test]
+fn test_link_checker_extract_attributes() {
+    let start_url = serve(vec![
+        (
+            "/",
+            200,
+            "text/html",
+            r#"<img src="placeholder.gif" data-src="photo.jpg" data-srcset="photo-480.jpg 480w, missing-960.jpg 960w"> <div data-bg="gone.jpg"></div>"#,
+        ),
+        ("/placeholder.gif", 200, "image/gif", ""),
+        ("/photo.jpg", 200, "image/jpeg", ""),
+        ("/photo-480.jpg", 200, "image/jpeg", ""),
+    ]);
+
+    let results = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .extract_attributes(["data-src", "data-srcset", "data-bg"])
+        .run(start_url.clone());
+    let mut bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    bad_urls.sort();
+    assert_eq!(bad_urls, vec![start_url.join("gone.jpg").unwrap().as_str(), start_url.join("missing-960.jpg").unwrap().as_str()]);
+    assert!(results.bad_urls.iter().all(|bad_url| bad_url.found_on == vec![start_url.to_string()]));
+    assert_eq!(results.requests, RequestCounts { head: 5, get: 1, retries: 0 });
+}
+
+#[test]
+fn test_link_checker_status_counts() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="old/">old</a> <a href="missing/">missing</a> <a href="error/">error</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../missing/">missing</a>"#),
+        ("/old/", 301, "text/html", "/a/"),
+        ("/error/", 500, "text/html", "error"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).max_retries(0).run(start_url.clone());
+    // The redirect and the page it leads to are both counted, and a URL is checked once however many pages link to it
+    assert_eq!(results.status_counts(), BTreeMap::from([(200, 3), (301, 1), (404, 1), (500, 1)]));
+    assert!(UrlResults::default().status_counts().is_empty());
+}
+
+#[test]
+fn test_link_checker_resolve() {
+    let served = serve(vec![
+        ("/", 200, "text/html", r#"<a href="page/">page</a> <a href="missing/">missing</a>"#),
+        ("/page/", 200, "text/html", "page"),
+    ]);
+    // The .test top-level domain never resolves, so the crawl only works through the override
+    let start_url = Url::parse(&format!("http://Staging.Test:{}/", served.port().unwrap())).unwrap();
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .resolve([("STAGING.test", "127.0.0.1".parse().unwrap())])
+        .run(start_url.clone());
+    assert_eq!(results.url_map.len(), 2);
+    assert_eq!(results.bad_urls.len(), 1);
+    assert_eq!(results.bad_urls[0].url, start_url.join("missing/").unwrap().as_str());
+    assert_eq!(results.bad_urls[0].status, Some(404));
+}
+
+#[test]
+fn test_link_checker_use_canonical() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="article/">article</a> <a href="article/?utm_source=home">tracked</a>"#),
+        ("/article/", 200, "text/html", r#"<link rel="canonical" href="/article/"> <a href="related/">related</a>"#),
+        ("/article/?utm_source=home", 200, "text/html", r#"<link rel="canonical" href="/article/"> <a href="missing/">missing</a>"#),
+        ("/article/related/", 200, "text/html", "related"),
+    ]);
+    let missing = start_url.join("article/missing/").unwrap();
+
+    // Without the flag, both copies of the article are crawled
+    let results = LinkChecker::new().threads(1).run(start_url.clone());
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![missing.as_str()]);
+
+    // With it, the copy is checked but its links aren't followed
+    let results = LinkChecker::new().threads(1).use_canonical(true).run(start_url.clone());
+    let tracked = start_url.join("article/?utm_source=home").unwrap();
+    assert!(results.bad_urls.is_empty());
+    assert!(results.checked_urls.iter().any(|checked| checked.url == tracked.as_str()));
+    assert!(results.url_map.contains_key(start_url.join("article/related/").unwrap().as_str()));
+}
+
+#[test]
+fn test_link_checker_use_canonical_header() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="api/item">item</a> <a href="api/item?ref=home">tracked</a>"#),
+        ("/api/item", 200, "text/html", r#"<a href="related">related</a>"#),
+        ("/api/item?ref=home", 200, "text/html", r#"<a href="missing">missing</a>"#),
+        ("/api/related", 200, "text/html", "related"),
+    ]);
+
+    let results = LinkChecker::new().threads(1).add_trailing_slashes(false).run(start_url.clone());
+    assert_eq!(results.bad_urls.len(), 1);
+
+    // The copy declares the first page as canonical in its Link header, so its links aren't followed
+    let results = LinkChecker::new().threads(1).add_trailing_slashes(false).use_canonical(true).run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+    assert!(results.url_map.contains_key(start_url.join("api/related").unwrap().as_str()));
+}
+
+#[test]
+fn test_link_checker_request_durations() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="page-slow">slow</a> <a href="missing.html">missing</a>"#),
+        ("/page-slow", 200, "text/html", "slow"),
+    ]);
+
+    let results = LinkChecker::new().threads(2).add_trailing_slashes(false).run(start_url.clone());
+    let duration = |url: &str| results.checked_urls.iter().find(|checked| checked.url == url).unwrap().duration_ms;
+    assert!(duration(start_url.join("page-slow").unwrap().as_str()).is_some_and(|ms| ms >= 200));
+    assert!(duration(start_url.as_str()).is_some_and(|ms| ms < 200));
+    assert_eq!(duration(start_url.join("missing.html").unwrap().as_str()), None);
+}
+
+#[test]
+fn test_link_checker_delay() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="b/">b</a>"#),
+        ("*", 200, "text/html", ""),
+    ]);
+
+    let start = std::time::Instant::now();
+    let results = LinkChecker::new()
+        .threads(1)
+        .delay(Some(std::time::Duration::from_millis(150)))
+        .run(start_url);
+
+    // The single thread pauses after the start page and after the first link
+    assert_eq!(results.checked_urls.len(), 3);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(300));
+}
+
+#[test]
+fn test_link_checker_save_and_resume() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../b/">b</a> <a href="../missing-a/">missing</a>"#),
+        ("/b/", 200, "text/html", r#"<a href="../missing-b/">missing</a>"#),
+    ]);
+    let state_path = std::env::temp_dir().join(format!("link-checker-resume-{}.json", std::process::id()));
+
+    let first_run = LinkChecker::new()
+        .threads(1)
+        .max_pages(Some(2))
+        .save_state(Some(state_path.clone()))
+        .run(start_url.clone());
+    assert_eq!(first_run.checked_urls.len(), 2);
+
+    let saved = SavedCrawl::load(&state_path).unwrap();
+    assert_eq!(saved.pending.len(), 2);
+    let resumed = LinkChecker::new()
+        .threads(1)
+        .resume(Some(saved))
+        .save_state(Some(state_path.clone()))
+        .run(start_url.clone());
+
+    // The resumed crawl only checks the unfinished URLs, but reports everything
+    assert_eq!(resumed.checked_urls.len(), 5);
+    let mut bad_urls: Vec<&str> = resumed.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    bad_urls.sort();
+    assert_eq!(
+        bad_urls,
+        vec![
+            start_url.join("missing-a/").unwrap().as_str(),
+            start_url.join("missing-b/").unwrap().as_str(),
+        ]
+    );
+    assert!(SavedCrawl::load(&state_path).unwrap().pending.is_empty());
+    std::fs::remove_file(&state_path).unwrap();
+}
+
+#[test]
+fn test_link_checker_bloom_filter_and_streamed_url_map() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="b/">b</a>"#),
+        ("/a/", 200, "text/html", r#"<a href="../b/">b</a> <a href="../missing/">missing</a>"#),
+        ("/b/", 200, "text/html", r#"<a href="../a/">a</a> <a href="../missing/">missing</a>"#),
+    ]);
+    let url_map_path = std::env::temp_dir().join(format!("link-checker-url-map-{}.jsonl", std::process::id()));
+
+    let results = LinkChecker::new()
+        .threads(2)
+        .bloom_filter(true)
+        .stream_url_map(Some(url_map_path.clone()))
+        .run(start_url.clone());
+    // Each page is still checked once
+    assert_eq!(results.checked_urls.len(), 4);
+    assert!(results.url_map.is_empty());
+    assert_eq!(results.streamed_pages, 3);
+    assert_eq!(results.bad_urls.len(), 1);
+    assert!(results.bad_urls[0].url.ends_with("/missing/"));
+    // Only the page it was first found on, since the other links weren't kept
+    assert_eq!(results.bad_urls[0].found_on.len(), 1);
+
+    let stats = CrawlStats::new(&results, std::time::Duration::ZERO, false);
+    assert_eq!(stats.pages_crawled, 3);
+    assert_eq!(stats.unique_urls, 4);
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&url_map_path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    let start_page = lines.iter().find(|line| line["page"] == start_url.as_str()).unwrap();
+    assert_eq!(start_page["links"], serde_json::json!([start_url.join("a/").unwrap().as_str(), start_url.join("b/").unwrap().as_str()]));
+    std::fs::remove_file(&url_map_path).unwrap();
+}
+
+#[test]
+fn test_link_checker_graceful_shutdown() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a-slow">a</a> <a href="b-slow">b</a> <a href="c-slow">c</a> <a href="d-slow">d</a>"#),
+        ("*", 200, "text/html", "slow"),
+    ]);
+    let state_path = std::env::temp_dir().join(format!("link-checker-shutdown-{}.json", std::process::id()));
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    let stop = shutdown_flag.clone();
+
+    // Shut down as soon as the start page has been checked, while its links are being fetched
+    let results = LinkChecker::new()
+        .threads(2)
+        .add_trailing_slashes(false)
+        .shutdown_flag(shutdown_flag)
+        .on_progress(move |progress| {
+            if progress.checked >= 1 {
+                stop.store(true, Ordering::Relaxed);
+            }
+        })
+        .save_state(Some(state_path.clone()))
+        .run(start_url.clone());
+
+    // Requests that had started are reported, and the rest are saved for later
+    let saved = SavedCrawl::load(&state_path).unwrap();
+    std::fs::remove_file(&state_path).unwrap();
+    assert!(results.checked_urls.iter().all(|checked| checked.ok));
+    assert!((1..=3).contains(&results.checked_urls.len()));
+    assert_eq!(results.checked_urls.len() + saved.pending.len(), 5);
+    assert_eq!(results.url_map.len(), results.checked_urls.len());
+}
+
+#[test]
+fn test_link_checker_cookies() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="members/">members</a>"#),
+        ("/members/", 200, "text/html", ""),
+    ]);
+
+    let without_cookie = LinkChecker::new().threads(1).run(start_url.clone());
+    let statuses: Vec<Option<u16>> = without_cookie.bad_urls.iter().map(|bad_url| bad_url.status).collect();
+    assert_eq!(statuses, vec![Some(403)]);
+
+    let results = LinkChecker::new()
+        .threads(1)
+        .cookies(CookieJar::new(&start_url).with_cookie("session=abc").unwrap())
+        .run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+}
+
+#[test]
+fn test_link_checker_sitemap_cookies() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", ""),
+        (
+            "/sitemap.xml",
+            200,
+            "application/xml",
+            r#"<sitemapindex><sitemap><loc>http://localhost:{port}/members/sitemap.xml</loc></sitemap></sitemapindex>"#,
+        ),
+        ("/members/sitemap.xml", 200, "application/xml", r#"<urlset><url><loc>http://localhost:{port}/missing/</loc></url></urlset>"#),
+    ]);
+
+    // The sitemap is fetched with the crawl's cookies, like its pages
+    let without_cookie = LinkChecker::new().threads(1).sitemap(true).run(start_url.clone());
+    assert!(without_cookie.bad_urls.is_empty());
+
+    let results = LinkChecker::new()
+        .threads(1)
+        .sitemap(true)
+        .cookies(CookieJar::new(&start_url).with_cookie("session=abc").unwrap())
+        .run(start_url.clone());
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("missing/").unwrap().as_str()]);
+}
+
+#[test]
+fn test_link_checker_login() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="members/">members</a>"#),
+        ("/members/", 200, "text/html", ""),
+    ]);
+    let login_url = start_url.join("/login").unwrap();
+    let checker = || LinkChecker::new().threads(1).cookies(CookieJar::new(&start_url));
+
+    let rejected = checker().login(&login_url, "user=me&pass=wrong").unwrap_err();
+    assert!(rejected.to_string().contains("status 401"), "{}", rejected);
+    let other_host = Url::parse("http://other.example/login").unwrap();
+    assert!(matches!(checker().login(&other_host, "pass=secret"), Err(LoginError::OtherHost(_))));
+
+    // The session cookie set by the login response is sent with every later request
+    let link_checker = checker();
+    link_checker.login(&login_url, "user=me&pass=secret").unwrap();
+    let results = link_checker.run(start_url.clone());
+    assert!(results.bad_urls.is_empty());
+}
+
+#[test]
+fn test_link_checker_soft_404() {
+    let not_found = "<html><head><title>Oops</title></head><body>We couldn't find that page.</body></html>";
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<html><head><title>Home</title></head><body><a href="docs/">docs</a> <a href="gone/">gone</a></body></html>"#),
+        ("/docs/", 200, "text/html", "<html><head><title>Oops</title></head><body>Well, this one is a real page after all.</body></html>"),
+        ("*", 200, "text/html", not_found),
+    ]);
+
+    let without_detection = LinkChecker::new().threads(2).run(start_url.clone());
+    assert!(without_detection.bad_urls.is_empty());
+
+    let results = LinkChecker::new().threads(2).detect_soft_404(true).run(start_url.clone());
+    let bad_urls: Vec<(&str, ErrorKind)> = results
+        .bad_urls
+        .iter()
+        .map(|bad_url| (bad_url.url.as_str(), bad_url.error_kind))
+        .collect();
+    assert_eq!(bad_urls, vec![(start_url.join("gone/").unwrap().as_str(), ErrorKind::Soft404)]);
+}
+
+#[test]
+fn test_link_checker_progress() {
+    let start_url = serve(vec![
+        ("/", 200, "text/html", r#"<a href="a/">a</a> <a href="missing/">missing</a>"#),
+        ("/a/", 200, "text/html", ""),
+    ]);
+    let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = updates.clone();
+
+    LinkChecker::new()
+        .threads(1)
+        .on_progress(move |progress| recorded.lock().unwrap().push(progress.clone()))
+        .run(start_url);
+
+    let updates = updates.lock().unwrap();
+    assert_eq!(updates.first().map(|progress| progress.pending), Some(1));
+    // Counters only grow, and the last update comes before the final result is received
+    assert!(updates.windows(2).all(|pair| pair[0].checked <= pair[1].checked && pair[0].broken <= pair[1].broken));
+    assert_eq!(updates.last().map(|progress| progress.checked), Some(2));
+}
+
+#[test]
+fn test_link_checker_multiple_seeds() {
+    let start_url = serve(vec![
+        ("/guides/", 200, "text/html", r#"<a href="intro.html">intro</a>"#),
+        ("/guides/intro.html", 200, "text/html", ""),
+        ("/reference/", 200, "text/html", r#"<a href="missing.html">missing</a>"#),
+    ]);
+    let seeds = vec![
+        start_url.join("guides/").unwrap(),
+        start_url.join("reference/").unwrap(),
+    ];
+
+    let results = LinkChecker::new().threads(2).run_seeds(seeds);
+
+    // Both disconnected sections are crawled
+    assert!(results.url_map.contains_key(start_url.join("guides/intro.html").unwrap().as_str()));
+    let bad_urls: Vec<&str> = results.bad_urls.iter().map(|bad_url| bad_url.url.as_str()).collect();
+    assert_eq!(bad_urls, vec![start_url.join("reference/missing.html").unwrap().as_str()]);
+}
+
+#[test]
+fn test_check_markdown_dir() {
+    let site_url = serve(vec![
+        ("/ok.html", 200, "text/html", ""),
+        ("/api/", 200, "text/html", ""),
+    ]);
+    let dir = std::env::temp_dir().join(format!("link-checker-markdown-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("guide")).unwrap();
+    std::fs::write(
+        dir.join("README.md"),
+        format!("[Guide](guide/intro.md) [Site]({site_url}ok.html) [Gone]({site_url}gone.html) ![Logo](guide/logo.png)\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("guide/intro.md"),
+        "[Back](../README.md#top) ![Logo](logo.png) [API](/api/) [Skipped](#section) `[code](nowhere.md)`\n",
+    )
+    .unwrap();
+
+    let results = check_markdown_dir(&LinkChecker::new().threads(2), &dir, Some(&site_url)).unwrap();
+    let intro_url = Url::from_file_path(dir.canonicalize().unwrap().join("guide/intro.md")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut bad_urls: Vec<(&str, Vec<&str>, ErrorKind)> = results
+        .bad_urls
+        .iter()
+        .map(|bad_url| (bad_url.url.as_str(), bad_url.found_on.iter().map(String::as_str).collect(), bad_url.error_kind))
+        .collect();
+    bad_urls.sort_by_key(|(url, _, _)| *url);
+    let gone_url = site_url.join("gone.html").unwrap();
+    let readme_url = intro_url.join("../README.md").unwrap();
+    let logo_url = intro_url.join("logo.png").unwrap();
+    // The missing logo is reported once, with both files linking to it
+    assert_eq!(
+        bad_urls,
+        vec![
+            (logo_url.as_str(), vec![readme_url.as_str(), intro_url.as_str()], ErrorKind::MissingFile),
+            (gone_url.as_str(), vec![readme_url.as_str()], ErrorKind::HttpStatus),
+        ]
+    );
+    assert_eq!(results.url_map.len(), 2);
+    assert_eq!(
+        results.url_map[intro_url.as_str()],
+        vec![readme_url.as_str(), logo_url.as_str(), site_url.join("api/").unwrap().as_str()]
+    );
+    assert_eq!(results.checked_urls.len(), 7);
+}